@@ -1,6 +1,6 @@
 use node_template_runtime::{
-	AccountId, AuraConfig, BalancesConfig, GenesisConfig, GrandpaConfig, Signature, SudoConfig,
-	SystemConfig, WASM_BINARY,
+	AccountId, AuraConfig, BalancesConfig, GenesisConfig, GrandpaConfig, KittiesModuleConfig,
+	Signature, SudoConfig, SystemConfig, WASM_BINARY,
 };
 use sc_service::ChainType;
 use sp_consensus_aura::sr25519::AuthorityId as AuraId;
@@ -152,5 +152,15 @@ fn testnet_genesis(
 			key: Some(root_key),
 		},
 		transaction_payment: Default::default(),
+		kitties_module: KittiesModuleConfig {
+			// Pre-mint two kitties and their child so UIs have something to show.
+			kitties: vec![
+				(endowed_accounts[0].clone(), [1u8; 16], *b"genesis0", None),
+				(endowed_accounts[1].clone(), [2u8; 16], *b"genesis1", None),
+				(endowed_accounts[0].clone(), [3u8; 16], *b"genesis2", Some((0, 1))),
+			],
+			next_kitty_id: 3,
+			phantom: Default::default(),
+		},
 	}
 }
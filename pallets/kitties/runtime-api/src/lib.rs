@@ -0,0 +1,22 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use codec::Codec;
+use sp_runtime::Perbill;
+use sp_std::vec::Vec;
+
+sp_api::decl_runtime_apis! {
+    /// Kitty相关的查询接口
+    pub trait KittiesApi<KittyId, MysteryBoxId, Listing>
+    where
+        KittyId: Codec,
+        MysteryBoxId: Codec,
+        Listing: Codec,
+    {
+        /// 根据已登记的名称查询kitty
+        fn kitty_by_name(name: [u8; 8]) -> Option<KittyId>;
+        /// 分页查询未过期的公开在售，从start之后开始，最多返回limit条
+        fn public_listings(start: Option<KittyId>, limit: u32) -> Vec<(KittyId, Listing)>;
+        /// 查询盲盒各稀有度的概率，盲盒不存在时为空
+        fn mystery_box_odds(box_id: MysteryBoxId) -> Vec<(u8, Perbill)>;
+    }
+}
@@ -21,61 +21,393 @@ mod migrations;
 pub mod pallet {
     use frame_support::pallet_prelude::*;
     use frame_system::pallet_prelude::*;
+    use frame_support::inherent::Vec;
 
     use sp_io::hashing::blake2_128;
     use frame_support::traits::{Randomness, Currency, ExistenceRequirement};
+    use frame_support::traits::tokens::fungibles;
     use frame_support::PalletId;
-    use sp_runtime::traits::AccountIdConversion;
+    use sp_runtime::Perbill;
+    use sp_runtime::traits::{AccountIdConversion, Hash, IdentifyAccount, One, Saturating, UniqueSaturatedInto, Verify, Zero};
     use crate::migrations;
     pub use crate::migrations::current_version::*;
 
-    pub type BalanceOf<T> =
-    <<T as Config>::Currency as Currency<<T as frame_system::Config>::AccountId>>::Balance;
+    pub type BalanceOf<T, I = ()> =
+    <<T as Config<I>>::Currency as Currency<<T as frame_system::Config>::AccountId>>::Balance;
+    pub type AssetIdOf<T, I = ()> =
+    <<T as Config<I>>::Assets as fungibles::Inspect<<T as frame_system::Config>::AccountId>>::AssetId;
+    pub type AssetBalanceOf<T, I = ()> =
+    <<T as Config<I>>::Assets as fungibles::Inspect<<T as frame_system::Config>::AccountId>>::Balance;
+    /// 在售信息
+    pub type ListingOf<T, I = ()> = Listing<
+        <T as frame_system::Config>::AccountId,
+        <T as frame_system::Config>::BlockNumber,
+        AssetIdOf<T, I>,
+        AssetBalanceOf<T, I>,
+    >;
+
+    /// 打包出售的ID
+    pub type BundleId = u32;
+
+    /// 投票的ID
+    pub type PollId = u32;
+
+    /// 盲盒的ID
+    pub type MysteryBoxId = u32;
+
+    /// 稀有度等级数，与rarity_weight的分档一致
+    pub const RARITY_TIERS: u32 = 4;
+
+    /// 每次查询公开在售的最大条数
+    pub const MAX_LISTINGS_PAGE: u32 = 100;
+
+    /// 盲盒的稀有度权重表(稀有度, 权重)
+    pub type RarityTable = BoundedVec<(u8, u32), ConstU32<RARITY_TIERS>>;
+
+    /// 白名单证明的最大深度
+    const MAX_PROOF_DEPTH: usize = 32;
+
+    /// 卖家在链下签名的出售订单
+    #[derive(Encode, Decode, Clone, Copy, RuntimeDebug, PartialEq, Eq, TypeInfo, MaxEncodedLen)]
+    pub struct Order<Balance, BlockNumber> {
+        pub kitty_id: KittyId,
+        pub price: Balance,
+        /// 过期区块
+        pub expires_at: BlockNumber,
+        /// 卖家的订单序号，用于撤销旧订单
+        pub nonce: u64,
+    }
+
+    /// 由dna决定的战斗属性
+    #[derive(Clone, Copy, RuntimeDebug, PartialEq, Eq)]
+    pub struct KittyStats {
+        pub attack: u8,
+        pub defense: u8,
+        pub speed: u8,
+    }
+
+    impl KittyStats {
+        pub fn from_dna(dna: &[u8; 16]) -> Self {
+            KittyStats { attack: dna[1], defense: dna[2], speed: dna[3] }
+        }
+    }
 
 
     #[pallet::pallet]
     #[pallet::storage_version(STORAGE_VERSION)]
-    pub struct Pallet<T>(_);
+    pub struct Pallet<T, I = ()>(PhantomData<(T, I)>);
 
     /// Configure the pallet by specifying the parameters and types on which it depends.
     #[pallet::config]
-    pub trait Config: frame_system::Config {
+    pub trait Config<I: 'static = ()>: frame_system::Config {
         /// Because this pallet emits events, it depends on the runtime's definition of an event.
-        type RuntimeEvent: From<Event<Self>> + IsType<<Self as frame_system::Config>::RuntimeEvent>;
+        type RuntimeEvent: From<Event<Self, I>> + IsType<<Self as frame_system::Config>::RuntimeEvent>;
         type Randomness: Randomness<Self::Hash, Self::BlockNumber>;
         type Currency: Currency<Self::AccountId>;
+        /// 在售kitty可以指定以其中的资产支付
+        type Assets: fungibles::Transfer<Self::AccountId>;
         #[pallet::constant]
-        type KittyPrice: Get<BalanceOf<Self>>;
+        type KittyPrice: Get<BalanceOf<Self, I>>;
         type PalletId: Get<PalletId>;
+        /// Kitty的最大发行量
+        #[pallet::constant]
+        type MaxSupply: Get<u32>;
+        /// 每个区块最多创建的Kitty数量
+        #[pallet::constant]
+        type MaxMintsPerBlock: Get<u32>;
+        /// 每个账户在一个区块内最多创建的Kitty数量
+        #[pallet::constant]
+        type MaxMintsPerAccount: Get<u32>;
+        /// 管理员权限，用于强制转移、销毁、下架和冻结kitty
+        type ForceOrigin: EnsureOrigin<Self::RuntimeOrigin>;
+        /// 白名单创建Kitty的优惠价格
+        #[pallet::constant]
+        type AllowlistPrice: Get<BalanceOf<Self, I>>;
+        /// 打包出售的最大kitty数量
+        #[pallet::constant]
+        type MaxBundleSize: Get<u32>;
+        /// 批量操作的最大数量
+        #[pallet::constant]
+        type MaxBatchSize: Get<u32>;
+        /// 每个区块最多到期的条目数
+        #[pallet::constant]
+        type MaxExpiriesPerBlock: Get<u32>;
+        /// 待接收的转移在多少个区块后过期
+        #[pallet::constant]
+        type PendingTransferExpiry: Get<Self::BlockNumber>;
+        /// 两阶段繁衍、战斗和盲盒在提交后多少个区块确定随机数
+        #[pallet::constant]
+        type BreedRevealDelay: Get<Self::BlockNumber>;
+        /// 交换要约在多少个区块后过期
+        #[pallet::constant]
+        type SwapExpiry: Get<Self::BlockNumber>;
+        /// 链下订单的签名
+        type OffchainSignature: Verify<Signer = Self::OffchainPublic> + Parameter;
+        /// 链下订单签名者的公钥
+        type OffchainPublic: IdentifyAccount<AccountId = Self::AccountId>;
+        /// 参战的kitty在战斗后多少个区块内不能再战斗
+        #[pallet::constant]
+        type BattleCooldown: Get<Self::BlockNumber>;
+        /// 战斗双方各自押注的金额，败者的押注归pallet账户
+        #[pallet::constant]
+        type BattleStake: Get<BalanceOf<Self, I>>;
+        /// 胜者从pallet账户获得的奖励，不能超过押注
+        #[pallet::constant]
+        type BattleReward: Get<BalanceOf<Self, I>>;
+        /// 每次喂养支付给pallet账户的费用
+        #[pallet::constant]
+        type FeedPrice: Get<BalanceOf<Self, I>>;
+        /// 每次喂养获得的经验
+        #[pallet::constant]
+        type XpPerFeed: Get<u32>;
+        /// 每升一级所需的经验
+        #[pallet::constant]
+        type XpPerLevel: Get<u32>;
+        /// 达到该等级后可以进行一次dna变异
+        #[pallet::constant]
+        type MutationLevel: Get<u8>;
+        /// 每次变异支付给pallet账户的费用
+        #[pallet::constant]
+        type MutationPrice: Get<BalanceOf<Self, I>>;
+        /// 质押的kitty每个区块每单位稀有度获得的奖励
+        #[pallet::constant]
+        type StakingRewardPerBlock: Get<BalanceOf<Self, I>>;
+        /// 抵押借款的最大额度占kitty价格的比例
+        #[pallet::constant]
+        type LoanToValue: Get<Perbill>;
+        /// 抵押借款到期需支付的利息比例
+        #[pallet::constant]
+        type LoanInterest: Get<Perbill>;
+        /// 投票的最大选项数
+        #[pallet::constant]
+        type MaxPollOptions: Get<u32>;
     }
 
     /// 存储KittyId
     #[pallet::storage]
     #[pallet::getter(fn next_kitty_id)]
-    pub type NextKittyId<T> = StorageValue<_, KittyId, ValueQuery>;     // 此处给定了第三个参数，该参数用于给定默认值，对于u32类型的KittyId来说，它就是0
+    pub type NextKittyId<T: Config<I>, I: 'static = ()> = StorageValue<_, KittyId, ValueQuery>;     // 此处给定了第三个参数，该参数用于给定默认值，对于u32类型的KittyId来说，它就是0
 
     /// 存储Kitty的数据内容
     #[pallet::storage]
     #[pallet::getter(fn kitties)]
-    pub type Kitties<T> = StorageMap<_, Blake2_128Concat, KittyId, Kitty>;
+    pub type Kitties<T: Config<I>, I: 'static = ()> = StorageMap<_, Blake2_128Concat, KittyId, Kitty>;
     /// 存储Kitty的Owner
     #[pallet::storage]
     #[pallet::getter(fn kitty_owner)]
-    pub type KittyOwner<T: Config> = StorageMap<_, Blake2_128Concat, KittyId, T::AccountId>;
+    pub type KittyOwner<T: Config<I>, I: 'static = ()> = StorageMap<_, Blake2_128Concat, KittyId, T::AccountId>;
     /// 存储Kitty的继承关系
     #[pallet::storage]
     #[pallet::getter(fn kitty_parents)]
-    pub type KittyParents<T: Config> = StorageMap<_, Blake2_128Concat, KittyId, (KittyId, KittyId), OptionQuery>;
+    pub type KittyParents<T: Config<I>, I: 'static = ()> = StorageMap<_, Blake2_128Concat, KittyId, (KittyId, KittyId), OptionQuery>;
     /// 存储Kitty的Sale状态
     #[pallet::storage]
     #[pallet::getter(fn kitty_on_sale)]
-    pub type KittyOnSale<T: Config> = StorageMap<_, Blake2_128Concat, KittyId, (), OptionQuery>;
+    pub type KittyOnSale<T: Config<I>, I: 'static = ()> = StorageMap<_, Blake2_128Concat, KittyId, ListingOf<T, I>, OptionQuery>;
+    /// 存储下一个打包出售的ID
+    #[pallet::storage]
+    #[pallet::getter(fn next_bundle_id)]
+    pub type NextBundleId<T: Config<I>, I: 'static = ()> = StorageValue<_, BundleId, ValueQuery>;
+    /// 存储打包出售：(卖家, kitty列表, 价格)
+    #[pallet::storage]
+    #[pallet::getter(fn bundles)]
+    pub type Bundles<T: Config<I>, I: 'static = ()> = StorageMap<_, Blake2_128Concat, BundleId, (T::AccountId, BoundedVec<KittyId, T::MaxBundleSize>, BalanceOf<T, I>), OptionQuery>;
+    /// 存储kitty所在的打包出售
+    #[pallet::storage]
+    #[pallet::getter(fn kitty_bundle)]
+    pub type KittyBundle<T: Config<I>, I: 'static = ()> = StorageMap<_, Blake2_128Concat, KittyId, BundleId, OptionQuery>;
+    /// 存储待接收的Kitty转移：(接收人, 过期区块)
+    #[pallet::storage]
+    #[pallet::getter(fn pending_transfer)]
+    pub type PendingTransfers<T: Config<I>, I: 'static = ()> = StorageMap<_, Blake2_128Concat, KittyId, (T::AccountId, T::BlockNumber), OptionQuery>;
+    /// 存储Kitty的出租要约：(租借人, 租期, 租金)
+    #[pallet::storage]
+    #[pallet::getter(fn rental_offer)]
+    pub type RentalOffers<T: Config<I>, I: 'static = ()> = StorageMap<_, Blake2_128Concat, KittyId, (T::AccountId, T::BlockNumber, BalanceOf<T, I>), OptionQuery>;
+    /// 存储出租中的Kitty：(租借人, 归还区块)
+    #[pallet::storage]
+    #[pallet::getter(fn kitty_rental)]
+    pub type KittyRentals<T: Config<I>, I: 'static = ()> = StorageMap<_, Blake2_128Concat, KittyId, (T::AccountId, T::BlockNumber), OptionQuery>;
+    /// 存储Kitty的交换要约，key为发起人的kitty：(发起人, 对方的kitty, 补差价, 过期区块)
+    #[pallet::storage]
+    #[pallet::getter(fn swap_order)]
+    pub type SwapOrders<T: Config<I>, I: 'static = ()> = StorageMap<_, Blake2_128Concat, KittyId, (T::AccountId, KittyId, Option<BalanceOf<T, I>>, T::BlockNumber), OptionQuery>;
+    /// 存储盲盒的提交，key为将生成的kitty：(购买人, 盲盒, 购买时的稀有度权重表, 名字, 可开启的区块)
+    #[pallet::storage]
+    #[pallet::getter(fn mystery_box_commit)]
+    pub type MysteryBoxCommits<T: Config<I>, I: 'static = ()> = StorageMap<
+        _,
+        Blake2_128Concat,
+        KittyId,
+        (T::AccountId, MysteryBoxId, RarityTable, [u8; 8], T::BlockNumber),
+        OptionQuery,
+    >;
+    /// 存储待生效的变异：(变异人, 生效的区块)
+    #[pallet::storage]
+    #[pallet::getter(fn mutation_commit)]
+    pub type MutationCommits<T: Config<I>, I: 'static = ()> = StorageMap<_, Blake2_128Concat, KittyId, (T::AccountId, T::BlockNumber), OptionQuery>;
+    /// 存储两阶段繁衍的提交，key为子kitty：(繁衍人, 父代, 名字, 可生成dna的区块)
+    #[pallet::storage]
+    #[pallet::getter(fn breed_commit)]
+    pub type BreedCommits<T: Config<I>, I: 'static = ()> = StorageMap<_, Blake2_128Concat, KittyId, (T::AccountId, (KittyId, KittyId), [u8; 8], T::BlockNumber), OptionQuery>;
+    /// 存储两阶段繁衍提交的父代索引：(父代, 子kitty)
+    #[pallet::storage]
+    pub type BreedCommitsByParent<T: Config<I>, I: 'static = ()> = StorageDoubleMap<_, Blake2_128Concat, KittyId, Blake2_128Concat, KittyId, (), OptionQuery>;
+    /// 存储每个区块需要确定随机数的两阶段提交
+    #[pallet::storage]
+    pub type RevealQueue<T: Config<I>, I: 'static = ()> = StorageMap<_, Twox64Concat, T::BlockNumber, BoundedVec<KittyId, T::MaxExpiriesPerBlock>, ValueQuery>;
+    /// 存储两阶段提交在reveal区块确定的随机数
+    #[pallet::storage]
+    #[pallet::getter(fn reveal_seed)]
+    pub type RevealSeeds<T: Config<I>, I: 'static = ()> = StorageMap<_, Blake2_128Concat, KittyId, [u8; 16], OptionQuery>;
+    /// 存储被冻结的Kitty
+    #[pallet::storage]
+    #[pallet::getter(fn kitty_frozen)]
+    pub type FrozenKitties<T: Config<I>, I: 'static = ()> = StorageMap<_, Blake2_128Concat, KittyId, (), OptionQuery>;
+    /// 白名单的Merkle根，叶子为(账户, 额度)
+    #[pallet::storage]
+    #[pallet::getter(fn allowlist_root)]
+    pub type AllowlistRoot<T: Config<I>, I: 'static = ()> = StorageValue<_, T::Hash, OptionQuery>;
+    /// 各账户已使用的白名单额度
+    #[pallet::storage]
+    #[pallet::getter(fn allowlist_claimed)]
+    pub type AllowlistClaimed<T: Config<I>, I: 'static = ()> = StorageMap<_, Blake2_128Concat, T::AccountId, u32, ValueQuery>;
+    /// 当前区块已创建的Kitty数量
+    #[pallet::storage]
+    #[pallet::getter(fn block_mints)]
+    pub type BlockMints<T: Config<I>, I: 'static = ()> = StorageValue<_, u32, ValueQuery>;
+    /// 当前区块各账户已创建的Kitty数量
+    #[pallet::storage]
+    #[pallet::getter(fn account_mints)]
+    pub type AccountMints<T: Config<I>, I: 'static = ()> = StorageMap<_, Blake2_128Concat, T::AccountId, u32, ValueQuery>;
+    /// 存储每个区块到期归还的Kitty
+    #[pallet::storage]
+    pub type RentalExpiries<T: Config<I>, I: 'static = ()> = StorageMap<_, Twox64Concat, T::BlockNumber, BoundedVec<KittyId, T::MaxExpiriesPerBlock>, ValueQuery>;
+    /// 存储每个区块过期的在售Kitty
+    #[pallet::storage]
+    pub type ListingExpiries<T: Config<I>, I: 'static = ()> = StorageMap<_, Twox64Concat, T::BlockNumber, BoundedVec<KittyId, T::MaxExpiriesPerBlock>, ValueQuery>;
+    /// 下一个待清理过期在售的区块
+    #[pallet::storage]
+    pub type ListingPruneCursor<T: Config<I>, I: 'static = ()> = StorageValue<_, T::BlockNumber, ValueQuery>;
+    /// 已登记的唯一名称，一个名称最多属于一个kitty
+    #[pallet::storage]
+    #[pallet::getter(fn name_to_kitty)]
+    pub type NameToKitty<T: Config<I>, I: 'static = ()> = StorageMap<_, Blake2_128Concat, [u8; 8], KittyId, OptionQuery>;
+    /// 账户的最小有效订单序号，小于该序号的链下订单均已撤销
+    #[pallet::storage]
+    #[pallet::getter(fn min_order_nonce)]
+    pub type MinOrderNonce<T: Config<I>, I: 'static = ()> = StorageMap<_, Blake2_128Concat, T::AccountId, u64, ValueQuery>;
+    /// 已成交的链下订单
+    #[pallet::storage]
+    #[pallet::getter(fn order_used)]
+    pub type UsedOrders<T: Config<I>, I: 'static = ()> = StorageDoubleMap<_, Blake2_128Concat, T::AccountId, Twox64Concat, u64, (), OptionQuery>;
+    /// 存储kitty的战绩(胜场, 负场)
+    #[pallet::storage]
+    #[pallet::getter(fn battle_record)]
+    pub type BattleRecords<T: Config<I>, I: 'static = ()> = StorageMap<_, Blake2_128Concat, KittyId, (u32, u32), ValueQuery>;
+    /// 存储参战kitty的冷却结束区块
+    #[pallet::storage]
+    #[pallet::getter(fn battle_cooldown)]
+    pub type BattleCooldowns<T: Config<I>, I: 'static = ()> = StorageMap<_, Blake2_128Concat, KittyId, T::BlockNumber, OptionQuery>;
+    /// 存储战斗挑战，key为挑战方的kitty：(挑战方, 对方的kitty, 接受后的(对方, 战斗区块))
+    #[pallet::storage]
+    #[pallet::getter(fn battle_challenge)]
+    pub type BattleChallenges<T: Config<I>, I: 'static = ()> = StorageMap<
+        _,
+        Blake2_128Concat,
+        KittyId,
+        (T::AccountId, KittyId, Option<(T::AccountId, T::BlockNumber)>),
+        OptionQuery,
+    >;
+    /// 存储质押中的kitty(质押人, 上次结算奖励的区块)
+    #[pallet::storage]
+    #[pallet::getter(fn staked_kitty)]
+    pub type StakedKitties<T: Config<I>, I: 'static = ()> = StorageMap<_, Blake2_128Concat, KittyId, (T::AccountId, T::BlockNumber), OptionQuery>;
+    /// 存储抵押借款请求(借款人, 金额, 期限)
+    #[pallet::storage]
+    #[pallet::getter(fn loan_request)]
+    pub type LoanRequests<T: Config<I>, I: 'static = ()> = StorageMap<_, Blake2_128Concat, KittyId, (T::AccountId, BalanceOf<T, I>, T::BlockNumber), OptionQuery>;
+    /// 存储进行中的抵押借款(借款人, 出借人, 本金, 到期区块)
+    #[pallet::storage]
+    #[pallet::getter(fn loan)]
+    pub type Loans<T: Config<I>, I: 'static = ()> = StorageMap<_, Blake2_128Concat, KittyId, (T::AccountId, T::AccountId, BalanceOf<T, I>, T::BlockNumber), OptionQuery>;
+    /// 存储owner取得kitty的区块，用于判断投票快照时的持有人
+    #[pallet::storage]
+    #[pallet::getter(fn kitty_acquired_at)]
+    pub type KittyAcquiredAt<T: Config<I>, I: 'static = ()> = StorageMap<_, Blake2_128Concat, KittyId, T::BlockNumber, ValueQuery>;
+    /// 存储下一个投票的ID
+    #[pallet::storage]
+    #[pallet::getter(fn next_poll_id)]
+    pub type NextPollId<T: Config<I>, I: 'static = ()> = StorageValue<_, PollId, ValueQuery>;
+    /// 存储投票(选项, 各选项票数, 结束区块, 快照区块, 是否已结束)
+    #[pallet::storage]
+    #[pallet::getter(fn polls)]
+    pub type Polls<T: Config<I>, I: 'static = ()> = StorageMap<
+        _,
+        Blake2_128Concat,
+        PollId,
+        (BoundedVec<[u8; 8], T::MaxPollOptions>, BoundedVec<u32, T::MaxPollOptions>, T::BlockNumber, T::BlockNumber, bool),
+        OptionQuery,
+    >;
+    /// 存储每只kitty在投票中选择的选项，每只kitty在每个投票中只能投一次
+    #[pallet::storage]
+    #[pallet::getter(fn poll_vote)]
+    pub type PollVotes<T: Config<I>, I: 'static = ()> = StorageDoubleMap<_, Twox64Concat, PollId, Blake2_128Concat, KittyId, u32, OptionQuery>;
+    /// 存储盲盒的(价格, 稀有度权重表)
+    #[pallet::storage]
+    #[pallet::getter(fn mystery_box)]
+    pub type MysteryBoxes<T: Config<I>, I: 'static = ()> = StorageMap<_, Twox64Concat, MysteryBoxId, (BalanceOf<T, I>, RarityTable), OptionQuery>;
+    /// 存储每个区块到期的抵押借款
+    #[pallet::storage]
+    pub type LoanDeadlines<T: Config<I>, I: 'static = ()> = StorageMap<_, Twox64Concat, T::BlockNumber, BoundedVec<KittyId, T::MaxExpiriesPerBlock>, ValueQuery>;
+
+    /// 创世时预置的kitty，kitty_id按顺序从0开始
+    #[pallet::genesis_config]
+    pub struct GenesisConfig<T: Config<I>, I: 'static = ()> {
+        /// (owner, dna, name, parents)，parents必须在子代之前
+        pub kitties: Vec<(T::AccountId, [u8; 16], [u8; 8], Option<(KittyId, KittyId)>)>,
+        /// 初始的NextKittyId，不能小于预置kitty的数量
+        pub next_kitty_id: KittyId,
+        pub phantom: PhantomData<I>,
+    }
+
+    #[cfg(feature = "std")]
+    impl<T: Config<I>, I: 'static> Default for GenesisConfig<T, I> {
+        fn default() -> Self {
+            GenesisConfig { kitties: Vec::new(), next_kitty_id: 0, phantom: PhantomData }
+        }
+    }
+
+    #[pallet::genesis_build]
+    impl<T: Config<I>, I: 'static> GenesisBuild<T, I> for GenesisConfig<T, I> {
+        fn build(&self) {
+            for (index, (owner, dna, name, parents)) in self.kitties.iter().enumerate() {
+                let kitty_id = index as KittyId;
+                if let Some((kitty_id_1, kitty_id_2)) = *parents {
+                    assert!(kitty_id_1 != kitty_id_2, "Genesis kitty {} has the same parents", kitty_id);
+                    assert!(
+                        kitty_id_1 < kitty_id && kitty_id_2 < kitty_id,
+                        "Genesis kitty {} must come after its parents",
+                        kitty_id
+                    );
+                    KittyParents::<T, I>::insert(kitty_id, (kitty_id_1, kitty_id_2));
+                }
+                Kitties::<T, I>::insert(kitty_id, Kitty { dna: *dna, name: *name, soulbound: false, ..Default::default() });
+                KittyOwner::<T, I>::insert(kitty_id, owner);
+            }
+            assert!(
+                self.next_kitty_id as usize >= self.kitties.len(),
+                "Genesis next_kitty_id is lower than the number of genesis kitties"
+            );
+            NextKittyId::<T, I>::put(self.next_kitty_id);
+        }
+    }
 
     // Pallets use events to inform users when important changes are made.
     // https://docs.substrate.io/main-docs/build/events-errors/
     #[pallet::event]
     #[pallet::generate_deposit(pub (super) fn deposit_event)]
-    pub enum Event<T: Config> {
+    pub enum Event<T: Config<I>, I: 'static = ()> {
         /// Kitty创建成功
         KittyCreated { who: T::AccountId, kitty_id: KittyId, kitty: Kitty },
         /// Kitty breed成功
@@ -83,14 +415,116 @@ pub mod pallet {
         /// Kitty 转移成功
         KittyTransferred { who: T::AccountId, recipient: T::AccountId, kitty_id: KittyId },
         /// Kitty 销售上架
-        KittyOnSale { who: T::AccountId, kitty_id: KittyId },
+        KittyOnSale { who: T::AccountId, kitty_id: KittyId, buyer: Option<T::AccountId>, asset_price: Option<(AssetIdOf<T, I>, AssetBalanceOf<T, I>)> },
         /// Kitty被购买
         KittyBought { who: T::AccountId, kitty_id: KittyId },
+        /// Kitty 转移待接收
+        TransferOffered { who: T::AccountId, recipient: T::AccountId, kitty_id: KittyId, expires_at: T::BlockNumber },
+        /// Kitty 转移被接收
+        TransferAccepted { who: T::AccountId, from: T::AccountId, kitty_id: KittyId },
+        /// Kitty 转移被拒绝
+        TransferRejected { who: T::AccountId, kitty_id: KittyId },
+        /// Kitty 转移被撤销
+        TransferCancelled { who: T::AccountId, kitty_id: KittyId },
+        /// Kitty 出租要约
+        RentalOffered { who: T::AccountId, borrower: T::AccountId, kitty_id: KittyId, duration: T::BlockNumber, fee: BalanceOf<T, I> },
+        /// Kitty 出租要约被撤销
+        RentalCancelled { who: T::AccountId, kitty_id: KittyId },
+        /// Kitty 已租出
+        KittyLent { owner: T::AccountId, borrower: T::AccountId, kitty_id: KittyId, expires_at: T::BlockNumber },
+        /// Kitty 租期结束归还
+        KittyReturned { borrower: T::AccountId, kitty_id: KittyId },
+        /// Kitty 交换要约
+        SwapProposed { who: T::AccountId, kitty_id: KittyId, their_kitty_id: KittyId, top_up: Option<BalanceOf<T, I>>, expires_at: T::BlockNumber },
+        /// Kitty 交换完成
+        KittySwapped { who: T::AccountId, kitty_id: KittyId, proposer: T::AccountId, their_kitty_id: KittyId },
+        /// Kitty 交换要约被撤销
+        SwapCancelled { who: T::AccountId, kitty_id: KittyId },
+        /// Kitty 在售已过期
+        ListingExpired { kitty_id: KittyId },
+        /// 白名单Merkle根已更新
+        AllowlistRootSet { root: Option<T::Hash> },
+        /// Kitty 两阶段繁衍已提交
+        BreedCommitted { who: T::AccountId, kitty_id: KittyId, reveal_at: T::BlockNumber },
+        /// Kitty 两阶段繁衍因父代不可用而取消，退还的金额，揭示时父代不可用则不退款
+        BreedCancelled { who: T::AccountId, kitty_id: KittyId, refund: Option<BalanceOf<T, I>> },
+        /// Kitty 打包出售
+        BundleListed { who: T::AccountId, bundle_id: BundleId, kitty_ids: BoundedVec<KittyId, T::MaxBundleSize>, price: BalanceOf<T, I> },
+        /// Kitty 打包出售被购买
+        BundleBought { who: T::AccountId, bundle_id: BundleId },
+        /// Kitty 打包出售被撤销
+        BundleCancelled { who: T::AccountId, bundle_id: BundleId },
+        /// Kitty 打包出售因成员转移而失效
+        BundleInvalidated { bundle_id: BundleId },
+        /// 管理员强制转移Kitty
+        KittyForceTransferred { kitty_id: KittyId, from: Option<T::AccountId>, to: T::AccountId },
+        /// 管理员销毁Kitty
+        KittyBurned { kitty_id: KittyId, owner: Option<T::AccountId> },
+        /// 管理员下架Kitty
+        KittyForceUnlisted { kitty_id: KittyId },
+        /// 管理员冻结Kitty
+        KittyFrozen { kitty_id: KittyId },
+        /// 管理员解冻Kitty
+        KittyThawed { kitty_id: KittyId },
+        /// Kitty 登记名称
+        NameClaimed { who: T::AccountId, kitty_id: KittyId, name: [u8; 8] },
+        /// Kitty 名称登记被释放
+        NameReleased { kitty_id: KittyId, name: [u8; 8] },
+        /// Kitty 改名
+        KittyRenamed { who: T::AccountId, kitty_id: KittyId, name: [u8; 8] },
+        /// 链下订单成交
+        OrderFilled { who: T::AccountId, seller: T::AccountId, kitty_id: KittyId, price: BalanceOf<T, I>, nonce: u64 },
+        /// 撤销序号小于min_nonce的链下订单
+        OrdersCancelled { who: T::AccountId, min_nonce: u64 },
+        /// 发起战斗挑战并押注
+        ChallengeIssued { who: T::AccountId, kitty_id: KittyId, opponent_id: KittyId },
+        /// 对方接受挑战并押注，战斗在fight_at区块进行
+        ChallengeAccepted { who: T::AccountId, kitty_id: KittyId, opponent_id: KittyId, fight_at: T::BlockNumber },
+        /// 战斗挑战取消，押注已退还
+        ChallengeCancelled { who: T::AccountId, kitty_id: KittyId },
+        /// Kitty 战斗结束，胜者取回押注并获得奖励，转账失败时没有奖励
+        KittyBattled { who: T::AccountId, kitty_id: KittyId, opponent_id: KittyId, winner: KittyId, reward: Option<BalanceOf<T, I>> },
+        /// Kitty 被喂养
+        KittyFed { who: T::AccountId, kitty_id: KittyId, experience: u32, level: u8 },
+        /// Kitty 已提交变异，在reveal_at区块生效
+        MutationCommitted { who: T::AccountId, kitty_id: KittyId, reveal_at: T::BlockNumber },
+        /// Kitty dna变异
+        KittyMutated { who: T::AccountId, kitty_id: KittyId, dna: [u8; 16] },
+        /// Kitty 质押
+        KittyStaked { who: T::AccountId, kitty_id: KittyId },
+        /// Kitty 解除质押，pallet账户余额不足时未结算的奖励作废
+        KittyUnstaked { who: T::AccountId, kitty_id: KittyId, reward: Option<BalanceOf<T, I>> },
+        /// 领取质押奖励
+        StakingRewardClaimed { who: T::AccountId, kitty_id: KittyId, reward: BalanceOf<T, I> },
+        /// 以kitty抵押请求借款
+        LoanRequested { who: T::AccountId, kitty_id: KittyId, amount: BalanceOf<T, I>, duration: T::BlockNumber },
+        /// 抵押借款请求被撤销
+        LoanRequestCancelled { who: T::AccountId, kitty_id: KittyId },
+        /// 抵押借款已放款
+        LoanFunded { lender: T::AccountId, borrower: T::AccountId, kitty_id: KittyId, amount: BalanceOf<T, I>, deadline: T::BlockNumber },
+        /// 抵押借款已还款
+        LoanRepaid { who: T::AccountId, kitty_id: KittyId, repayment: BalanceOf<T, I> },
+        /// 抵押借款到期未还，kitty归出借人所有
+        LoanDefaulted { lender: T::AccountId, borrower: T::AccountId, kitty_id: KittyId },
+        /// 创建投票，快照区块之前取得kitty且仍持有的账户可以投票
+        PollCreated { poll_id: PollId, options: BoundedVec<[u8; 8], T::MaxPollOptions>, end: T::BlockNumber, snapshot: T::BlockNumber },
+        /// 以持有的kitty投票
+        Voted { who: T::AccountId, poll_id: PollId, option: u32, votes: u32 },
+        /// 投票结束，平票时没有胜出选项
+        PollClosed { poll_id: PollId, tally: BoundedVec<u32, T::MaxPollOptions>, winner: Option<u32> },
+        /// 设置盲盒
+        MysteryBoxSet { box_id: MysteryBoxId, price: BalanceOf<T, I>, table: RarityTable },
+        /// 移除盲盒
+        MysteryBoxRemoved { box_id: MysteryBoxId },
+        /// 购买盲盒，在reveal_at区块之后开启
+        MysteryBoxCommitted { who: T::AccountId, box_id: MysteryBoxId, kitty_id: KittyId, reveal_at: T::BlockNumber },
+        /// 开启盲盒，得到指定稀有度的kitty
+        MysteryBoxOpened { who: T::AccountId, box_id: MysteryBoxId, kitty_id: KittyId, rarity: u8 },
     }
 
     // Errors inform users that something went wrong.
     #[pallet::error]
-    pub enum Error<T> {
+    pub enum Error<T, I = ()> {
         /// KittyId创建失败
         InvalidKittyId,
         /// KittyId相同
@@ -107,14 +541,258 @@ pub mod pallet {
         AlreadyOwned,
         /// 未上架销售
         NotOnSale,
+        /// Kitty处于锁定状态
+        KittyLocked,
+        /// 没有待接收的转移
+        NoPendingTransfer,
+        /// 非转移接收人
+        NotRecipient,
+        /// 转移已过期
+        TransferExpired,
+        /// 超出批量操作的最大数量
+        BatchTooLarge,
+        /// 没有出租要约
+        NoRentalOffer,
+        /// 非租借人
+        NotBorrower,
+        /// 租期无效
+        InvalidDuration,
+        /// 同一区块到期的条目过多
+        TooManyExpiries,
+        /// 没有交换要约
+        NoSwapOrder,
+        /// 交换要约已过期
+        SwapExpired,
+        /// 在售已过期
+        ListingExpired,
+        /// 过期区块无效
+        InvalidExpiry,
+        /// 已达到最大发行量
+        MaxSupplyReached,
+        /// 已达到当前区块的创建上限
+        BlockMintLimitReached,
+        /// 已达到账户在当前区块的创建上限
+        AccountMintLimitReached,
+        /// 未设置白名单
+        NoAllowlist,
+        /// 白名单证明无效
+        InvalidProof,
+        /// 白名单额度已用完
+        AllowlistQuotaExceeded,
+        /// Kitty已被冻结
+        KittyFrozen,
+        /// Kitty未被冻结
+        NotFrozen,
+        /// 灵魂绑定的Kitty不能转移和出售
+        Soulbound,
+        /// 非指定买家
+        NotDesignatedBuyer,
+        /// 打包出售的kitty数量无效
+        InvalidBundle,
+        /// 打包出售不存在
+        NoBundle,
+        /// 没有繁衍提交
+        NoBreedCommit,
+        /// 尚未到达生成dna的区块
+        BreedNotReady,
+        /// 名称已被其他kitty登记
+        NameTaken,
+        /// kitty的名称未登记
+        NameNotClaimed,
+        /// 订单签名无效
+        InvalidSignature,
+        /// 订单已过期
+        OrderExpired,
+        /// 订单已成交或已撤销
+        OrderUsed,
+        /// 订单序号无效
+        InvalidNonce,
+        /// Kitty处于战斗冷却中
+        KittyInCooldown,
+        /// Kitty已经发起了战斗挑战
+        ChallengeExists,
+        /// 战斗挑战不存在
+        NoChallenge,
+        /// 战斗挑战已被接受
+        ChallengeAlreadyAccepted,
+        /// 不能挑战自己的kitty
+        CanNotBattleSelf,
+        /// 喂养次数无效
+        InvalidFeedCount,
+        /// 等级不足
+        LevelTooLow,
+        /// 已经变异过
+        AlreadyMutated,
+        /// 已有待生效的变异
+        MutationPending,
+        /// Kitty未质押
+        NotStaked,
+        /// 借款金额为零或超过抵押额度
+        InvalidLoanAmount,
+        /// 没有抵押借款请求
+        NoLoanRequest,
+        /// 没有进行中的抵押借款
+        NoLoan,
+        /// 稀有度不存在、重复、权重为0或总权重溢出
+        InvalidRarityTable,
+        /// 盲盒不存在
+        NoMysteryBox,
+        /// 没有待开启的盲盒
+        NoMysteryBoxCommit,
+        /// 盲盒尚未到达可开启的区块
+        MysteryBoxNotReady,
+        /// 投票的选项数或结束区块无效
+        InvalidPoll,
+        /// 投票不存在
+        NoPoll,
+        /// 投票已到结束区块
+        PollEnded,
+        /// 投票尚未到结束区块
+        PollNotEnded,
+        /// 投票已经结束
+        PollClosed,
+        /// 选项不存在
+        InvalidOption,
+        /// Kitty已经在该投票中投过票
+        AlreadyVoted,
+        /// Kitty在投票快照之后才产生或转移给当前owner
+        NotInSnapshot,
+        /// 投票没有指定kitty
+        EmptyVote,
     }
 
     #[pallet::hooks]
-    impl<T: Config> Hooks<BlockNumberFor<T>> for Pallet<T> {
+    impl<T: Config<I>, I: 'static> Hooks<BlockNumberFor<T>> for Pallet<T, I> {
+        /// 重置创建计数，并归还租期已到的kitty
+        fn on_initialize(n: BlockNumberFor<T>) -> Weight {
+            // 上个区块最多有MaxMintsPerBlock个账户创建过kitty
+            BlockMints::<T, I>::kill();
+            let cleared = AccountMints::<T, I>::clear(T::MaxMintsPerBlock::get(), None);
+            let mut weight = T::DbWeight::get().writes(1 + cleared.unique as u64);
+
+            let expired = RentalExpiries::<T, I>::take(n);
+            weight.saturating_accrue(T::DbWeight::get().reads_writes(1, 1));
+            for kitty_id in expired {
+                weight.saturating_accrue(T::DbWeight::get().reads(1));
+                // 仅归还本区块到期的租约
+                if let Some((borrower, expires_at)) = KittyRentals::<T, I>::get(kitty_id) {
+                    if expires_at == n {
+                        KittyRentals::<T, I>::remove(kitty_id);
+                        weight.saturating_accrue(T::DbWeight::get().writes(1));
+                        Self::deposit_event(Event::KittyReturned { borrower, kitty_id });
+                    }
+                }
+            }
+
+            let revealing = RevealQueue::<T, I>::take(n);
+            weight.saturating_accrue(T::DbWeight::get().reads_writes(1, 1));
+            for kitty_id in revealing {
+                weight.saturating_accrue(T::DbWeight::get().reads(1));
+                // 随机数在reveal区块确定，之后何时reveal都得到相同的dna
+                if Self::breed_commit(kitty_id).map(|(_, _, _, reveal_at)| reveal_at) == Some(n) {
+                    let (seed, _) = T::Randomness::random(&(b"kitties/breed", kitty_id).encode());
+                    RevealSeeds::<T, I>::insert(kitty_id, seed.using_encoded(blake2_128));
+                    weight.saturating_accrue(T::DbWeight::get().writes(1));
+                }
+                if Self::mystery_box_commit(kitty_id).map(|(_, _, _, _, reveal_at)| reveal_at) == Some(n) {
+                    let (seed, _) = T::Randomness::random(&(b"kitties/box", kitty_id).encode());
+                    RevealSeeds::<T, I>::insert(kitty_id, seed.using_encoded(blake2_128));
+                    weight.saturating_accrue(T::DbWeight::get().writes(1));
+                }
+                // 变异在本区块生效，提交后kitty被锁定直到生效
+                if let Some((who, reveal_at)) = Self::mutation_commit(kitty_id) {
+                    if reveal_at == n {
+                        MutationCommits::<T, I>::remove(kitty_id);
+                        if let Some(mut kitty) = Self::kitties(kitty_id) {
+                            let (seed, _) = T::Randomness::random(&(b"kitties/mutate", kitty_id).encode());
+                            kitty.dna = Self::mutate_dna(kitty.dna, seed.using_encoded(blake2_128));
+                            kitty.mutated = true;
+                            Kitties::<T, I>::insert(kitty_id, &kitty);
+                            Self::deposit_event(Event::KittyMutated { who, kitty_id, dna: kitty.dna });
+                        }
+                        weight.saturating_accrue(T::DbWeight::get().reads_writes(1, 2));
+                    }
+                }
+                // 已接受的战斗在本区块进行
+                if let Some((challenger, opponent_id, Some((opponent, fight_at)))) = Self::battle_challenge(kitty_id) {
+                    if fight_at == n {
+                        Self::settle_battle(kitty_id, challenger, opponent_id, opponent);
+                        weight.saturating_accrue(T::DbWeight::get().reads_writes(2, 5));
+                    }
+                }
+            }
+
+            let defaulted = LoanDeadlines::<T, I>::take(n);
+            weight.saturating_accrue(T::DbWeight::get().reads_writes(1, 1));
+            for kitty_id in defaulted {
+                weight.saturating_accrue(T::DbWeight::get().reads(1));
+                // 仅处理本区块到期且未还款的借款
+                if let Some((borrower, lender, _, deadline)) = Loans::<T, I>::get(kitty_id) {
+                    if deadline == n {
+                        Loans::<T, I>::remove(kitty_id);
+                        Self::set_owner(kitty_id, &lender);
+                        weight.saturating_accrue(T::DbWeight::get().writes(5));
+                        Self::deposit_event(Event::LoanDefaulted { lender, borrower, kitty_id });
+                    }
+                }
+            }
+            weight
+        }
+
+        /// 在剩余的weight内清理过期的在售
+        fn on_idle(n: BlockNumberFor<T>, remaining_weight: Weight) -> Weight {
+            let db_weight = T::DbWeight::get();
+            let mut used = db_weight.reads_writes(1, 1);
+            if used.any_gt(remaining_weight) {
+                return Weight::zero();
+            }
+            let mut cursor = ListingPruneCursor::<T, I>::get();
+            while cursor <= n {
+                let read = db_weight.reads_writes(1, 1);
+                if used.saturating_add(read).any_gt(remaining_weight) {
+                    break;
+                }
+                used.saturating_accrue(read);
+                let mut expiring = ListingExpiries::<T, I>::get(cursor);
+                while let Some(kitty_id) = expiring.last().copied() {
+                    let remove = db_weight.reads_writes(1, 1);
+                    if used.saturating_add(remove).any_gt(remaining_weight) {
+                        break;
+                    }
+                    used.saturating_accrue(remove);
+                    expiring.pop();
+                    // 重新上架或已售出的不处理
+                    if Self::kitty_on_sale(kitty_id).and_then(|listing| listing.expires_at) == Some(cursor) {
+                        KittyOnSale::<T, I>::remove(kitty_id);
+                        Self::deposit_event(Event::ListingExpired { kitty_id });
+                    }
+                }
+                if !expiring.is_empty() {
+                    // weight用尽，保留未处理的部分
+                    ListingExpiries::<T, I>::insert(cursor, expiring);
+                    break;
+                }
+                ListingExpiries::<T, I>::remove(cursor);
+                cursor = cursor.saturating_add(One::one());
+            }
+            ListingPruneCursor::<T, I>::put(cursor);
+            used
+        }
+
+        #[cfg(feature = "try-runtime")]
+        fn try_state(_n: BlockNumberFor<T>) -> Result<(), &'static str> {
+            Self::do_try_state()
+        }
+
+        fn integrity_test() {
+            // 奖励不超过败者的押注，战斗不会减少pallet账户的余额
+            assert!(T::BattleReward::get() <= T::BattleStake::get(), "BattleReward must not exceed BattleStake");
+        }
+
         fn on_runtime_upgrade() -> Weight {
             // migrations::v1::migrate::<T>()
             // migrations::v2::migrate::<T>()
-            migrations::migrate::<T>()
+            migrations::migrate::<T, I>()
         }
     }
 
@@ -122,161 +800,1614 @@ pub mod pallet {
     // These functions materialize as "extrinsics", which are often compared to transactions.
     // Dispatchable functions must be annotated with a weight and must return a DispatchResult.
     #[pallet::call]
-    impl<T: Config> Pallet<T> {
+    impl<T: Config<I>, I: 'static> Pallet<T, I> {
         /// 创建Kitty
         #[pallet::call_index(0)]
         #[pallet::weight(10_000 + T::DbWeight::get().writes(1).ref_time())]
         pub fn create_kitty(origin: OriginFor<T>, name: [u8; 8]) -> DispatchResult {
             let who = ensure_signed(origin)?;
 
-            let kitty_id = Self::get_next_id()?;
-            let dna = Self::random_value(&who);
-            let kitty = Kitty { dna, name };
+            Self::do_create_kitty(&who, name, T::KittyPrice::get())
+        }
+
+        /// 两个kitty，生成一个子kitty
+        #[pallet::call_index(1)]
+        #[pallet::weight(10_001 + T::DbWeight::get().writes(1).ref_time())]
+        pub fn breed(origin: OriginFor<T>, kitty_id_1: KittyId, kitty_id_2: KittyId, name: [u8; 8]) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+
+            Self::ensure_can_breed(&who, kitty_id_1, kitty_id_2)?;
+
+            let kitty_id = Self::reserve_mint(&who)?;
+
+            let selector = Self::random_value(&who, kitty_id);
+            let data = Self::breed_dna(kitty_id_1, kitty_id_2, selector)?;
+            let kitty = Kitty { dna: data, name, soulbound: false, ..Default::default() };
 
             let price = T::KittyPrice::get();
             // T::Currency::reserve(&who, price)?;
             T::Currency::transfer(&who, &Self::get_account_id(),
                                   price, ExistenceRequirement::KeepAlive)?;
 
-            Kitties::<T>::insert(kitty_id, &kitty);
-            KittyOwner::<T>::insert(kitty_id, &who);
-
+            Kitties::<T, I>::insert(kitty_id, &kitty);
+            Self::insert_owner(kitty_id, &who);
+            KittyParents::<T, I>::insert(kitty_id, (kitty_id_1, kitty_id_2));
 
             // 发布创建成功事件
-            Self::deposit_event(Event::KittyCreated { who, kitty_id, kitty });
-            // Return a successful DispatchResultWithPostInfo
+            Self::deposit_event(Event::KittyBred { who, kitty_id, kitty });
+
             Ok(())
         }
 
-        /// 两个kitty，生成一个子kitty
-        #[pallet::call_index(1)]
-        #[pallet::weight(10_001 + T::DbWeight::get().writes(1).ref_time())]
-        pub fn breed(origin: OriginFor<T>, kitty_id_1: KittyId, kitty_id_2: KittyId, name: [u8; 8]) -> DispatchResult {
+        /// 两阶段繁衍：先确定父代并支付，子kitty的dna在BreedRevealDelay个区块后由reveal_breed决定
+        #[pallet::call_index(18)]
+        #[pallet::weight(10_018 + T::DbWeight::get().writes(4).ref_time())]
+        pub fn commit_breed(origin: OriginFor<T>, kitty_id_1: KittyId, kitty_id_2: KittyId, name: [u8; 8]) -> DispatchResult {
             let who = ensure_signed(origin)?;
 
-            ensure!(kitty_id_1 != kitty_id_2,Error::<T>::SameKittyId);
-            ensure!(Kitties::<T>::contains_key(kitty_id_1),Error::<T>::InvalidKittyId);
-            ensure!(Kitties::<T>::contains_key(kitty_id_2),Error::<T>::InvalidKittyId);
+            Self::ensure_can_breed(&who, kitty_id_1, kitty_id_2)?;
 
-            let kitty_id = Self::get_next_id()?;
+            // 提交时即占用kittyId并支付
+            let kitty_id = Self::reserve_mint(&who)?;
+            T::Currency::transfer(&who, &Self::get_account_id(),
+                                  T::KittyPrice::get(), ExistenceRequirement::KeepAlive)?;
 
-            let kitty_1 = Kitties::<T>::get(kitty_id_1).expect("Checked it Exists");
-            let kitty_2 = Kitties::<T>::get(kitty_id_2).expect("Checked it Exists");
+            // 至少延迟一个区块，reveal区块的on_initialize尚未执行
+            let reveal_at = <frame_system::Pallet<T>>::block_number()
+                .saturating_add(T::BreedRevealDelay::get().max(One::one()));
+            RevealQueue::<T, I>::try_append(reveal_at, kitty_id)
+                .map_err(|_| Error::<T, I>::TooManyExpiries)?;
+            BreedCommits::<T, I>::insert(kitty_id, (&who, (kitty_id_1, kitty_id_2), name, reveal_at));
+            BreedCommitsByParent::<T, I>::insert(kitty_id_1, kitty_id, ());
+            BreedCommitsByParent::<T, I>::insert(kitty_id_2, kitty_id, ());
 
-            let selector = Self::random_value(&who);
-            let mut data = [0u8; 16];
-            for i in 0..kitty_1.dna.len() {
-                data[i] = (kitty_1.dna[i] & selector[i]) | (kitty_2.dna[i] & !selector[i])
-            }
-            let kitty = Kitty { dna: data, name };
+            Self::deposit_event(Event::BreedCommitted { who, kitty_id, reveal_at });
 
-            let price = T::KittyPrice::get();
-            // T::Currency::reserve(&who, price)?;
-            T::Currency::transfer(&who, &Self::get_account_id(),
-                                  price, ExistenceRequirement::KeepAlive)?;
+            Ok(())
+        }
 
-            Kitties::<T>::insert(kitty_id, &kitty);
-            KittyOwner::<T>::insert(kitty_id, &who);
-            KittyParents::<T>::insert(kitty_id, (kitty_id_1, kitty_id_2));
+        /// 到达reveal区块后，任何人都可以生成子kitty；父代已不可用时取消且不退款
+        #[pallet::call_index(19)]
+        #[pallet::weight(10_019 + T::DbWeight::get().writes(7).ref_time())]
+        pub fn reveal_breed(origin: OriginFor<T>, kitty_id: KittyId) -> DispatchResult {
+            ensure_signed(origin)?;
+
+            let (who, (kitty_id_1, kitty_id_2), name, reveal_at) = Self::breed_commit(kitty_id).ok_or(Error::<T, I>::NoBreedCommit)?;
+            ensure!(<frame_system::Pallet<T>>::block_number() >= reveal_at, Error::<T, I>::BreedNotReady);
+
+            // 使用reveal区块确定的随机数，提交时无法预知，之后也无法挑选
+            let selector = match Self::reveal_seed(kitty_id) {
+                Some(selector) if Self::ensure_can_use(&who, kitty_id_1).is_ok()
+                    && Self::ensure_can_use(&who, kitty_id_2).is_ok() => selector,
+                // 父代已不可用（如租期已到）时取消，支付的价格不退还，避免借取消重选结果
+                _ => {
+                    Self::cancel_breed_commit(kitty_id, false);
+                    return Ok(());
+                }
+            };
+            let data = Self::breed_dna(kitty_id_1, kitty_id_2, selector)?;
+            let kitty = Kitty { dna: data, name, soulbound: false, ..Default::default() };
+
+            Self::remove_breed_commit(kitty_id);
+            Kitties::<T, I>::insert(kitty_id, &kitty);
+            Self::insert_owner(kitty_id, &who);
+            KittyParents::<T, I>::insert(kitty_id, (kitty_id_1, kitty_id_2));
 
-            // 发布创建成功事件
             Self::deposit_event(Event::KittyBred { who, kitty_id, kitty });
 
             Ok(())
         }
 
-        /// 转移kitty
-        #[pallet::call_index(2)]
-        #[pallet::weight(10_002 + T::DbWeight::get().writes(1).ref_time())]
-        pub fn transfer(origin: OriginFor<T>, recipient: T::AccountId, kitty_id: KittyId) -> DispatchResult {
+        /// 设置白名单的Merkle根，None表示关闭白名单
+        #[pallet::call_index(20)]
+        #[pallet::weight(10_020 + T::DbWeight::get().writes(1).ref_time())]
+        pub fn set_allowlist_root(origin: OriginFor<T>, root: Option<T::Hash>) -> DispatchResult {
+            ensure_root(origin)?;
+
+            AllowlistRoot::<T, I>::set(root);
+
+            Self::deposit_event(Event::AllowlistRootSet { root });
+
+            Ok(())
+        }
+
+        /// 白名单账户以优惠价格创建Kitty，quota为叶子中记录的额度
+        #[pallet::call_index(21)]
+        #[pallet::weight(10_021 + T::DbWeight::get().writes(3).ref_time() + 1_000 * proof.len() as u64)]
+        pub fn create_kitty_allowlisted(origin: OriginFor<T>, name: [u8; 8], quota: u32, proof: Vec<T::Hash>) -> DispatchResult {
             let who = ensure_signed(origin)?;
 
-            ensure!(Kitties::<T>::contains_key(kitty_id),Error::<T>::InvalidKittyId);
+            let root = Self::allowlist_root().ok_or(Error::<T, I>::NoAllowlist)?;
+            ensure!(proof.len() <= MAX_PROOF_DEPTH, Error::<T, I>::InvalidProof);
+            let computed = proof.iter()
+                .fold(Self::allowlist_leaf(&who, quota), |node, sibling| Self::allowlist_node(node, *sibling));
+            ensure!(computed == root, Error::<T, I>::InvalidProof);
+
+            AllowlistClaimed::<T, I>::try_mutate(&who, |claimed| -> DispatchResult {
+                ensure!(*claimed < quota, Error::<T, I>::AllowlistQuotaExceeded);
+                *claimed += 1;
+                Ok(())
+            })?;
 
-            ensure!( Self::kitty_owner(kitty_id) == Some(who.clone()),Error::<T>::NotOwner);
+            Self::do_create_kitty(&who, name, T::AllowlistPrice::get())
+        }
+
+        /// 管理员强制转移kitty，同时清理在售、转移、交换和出租要约；借款、租借或质押中的kitty不能强制转移
+        #[pallet::call_index(22)]
+        #[pallet::weight(10_022 + T::DbWeight::get().writes(9).ref_time())]
+        pub fn force_transfer(origin: OriginFor<T>, kitty_id: KittyId, recipient: T::AccountId) -> DispatchResult {
+            T::ForceOrigin::ensure_origin(origin)?;
 
-            ensure!(recipient != who,Error::<T>::CanNotTransferToSelf);
+            ensure!(Kitties::<T, I>::contains_key(kitty_id), Error::<T, I>::InvalidKittyId);
+            // 出借人、租借人和质押奖励无法结算，需先还款、到期或解除质押
+            ensure!(!Loans::<T, I>::contains_key(kitty_id), Error::<T, I>::KittyLocked);
+            ensure!(!KittyRentals::<T, I>::contains_key(kitty_id), Error::<T, I>::KittyLocked);
+            ensure!(!StakedKitties::<T, I>::contains_key(kitty_id), Error::<T, I>::KittyLocked);
+            let from = Self::kitty_owner(kitty_id);
 
-            KittyOwner::<T>::insert(kitty_id, &recipient);
+            Self::clear_kitty_state(kitty_id);
+            Self::refund_breed_commits_of(kitty_id);
+            Self::set_owner(kitty_id, &recipient);
 
-            Self::deposit_event(Event::KittyTransferred { who, recipient, kitty_id });
+            Self::deposit_event(Event::KittyForceTransferred { kitty_id, from, to: recipient });
 
             Ok(())
         }
 
-        /// 标记可售
-        #[pallet::call_index(3)]
-        #[pallet::weight(10_003 + T::DbWeight::get().writes(1).ref_time())]
-        pub fn sale(origin: OriginFor<T>, kitty_id: KittyId) -> DispatchResult {
+        /// 管理员销毁kitty
+        #[pallet::call_index(23)]
+        #[pallet::weight(10_023 + T::DbWeight::get().writes(16).ref_time())]
+        pub fn force_burn(origin: OriginFor<T>, kitty_id: KittyId) -> DispatchResult {
+            T::ForceOrigin::ensure_origin(origin)?;
+
+            ensure!(Kitties::<T, I>::contains_key(kitty_id), Error::<T, I>::InvalidKittyId);
+            let owner = KittyOwner::<T, I>::take(kitty_id);
+
+            Self::clear_kitty_state(kitty_id);
+            Self::unregister_name(kitty_id);
+            // 以该kitty为父代的两阶段繁衍无法完成，取消并退款
+            Self::refund_breed_commits_of(kitty_id);
+            Kitties::<T, I>::remove(kitty_id);
+            KittyParents::<T, I>::remove(kitty_id);
+            FrozenKitties::<T, I>::remove(kitty_id);
+            Self::cancel_battle(kitty_id);
+            BattleRecords::<T, I>::remove(kitty_id);
+            BattleCooldowns::<T, I>::remove(kitty_id);
+            KittyAcquiredAt::<T, I>::remove(kitty_id);
+
+            Self::deposit_event(Event::KittyBurned { kitty_id, owner });
+
+            Ok(())
+        }
+
+        /// 管理员下架kitty
+        #[pallet::call_index(24)]
+        #[pallet::weight(10_024 + T::DbWeight::get().writes(1).ref_time())]
+        pub fn force_unlist(origin: OriginFor<T>, kitty_id: KittyId) -> DispatchResult {
+            T::ForceOrigin::ensure_origin(origin)?;
+
+            ensure!(KittyOnSale::<T, I>::contains_key(kitty_id), Error::<T, I>::NotOnSale);
+            KittyOnSale::<T, I>::remove(kitty_id);
+
+            Self::deposit_event(Event::KittyForceUnlisted { kitty_id });
+
+            Ok(())
+        }
+
+        /// 管理员冻结kitty，冻结后不能转移、出售、购买和繁衍
+        #[pallet::call_index(25)]
+        #[pallet::weight(10_025 + T::DbWeight::get().writes(1).ref_time())]
+        pub fn freeze_kitty(origin: OriginFor<T>, kitty_id: KittyId) -> DispatchResult {
+            T::ForceOrigin::ensure_origin(origin)?;
+
+            ensure!(Kitties::<T, I>::contains_key(kitty_id), Error::<T, I>::InvalidKittyId);
+            ensure!(!FrozenKitties::<T, I>::contains_key(kitty_id), Error::<T, I>::KittyFrozen);
+            FrozenKitties::<T, I>::insert(kitty_id, ());
+
+            Self::deposit_event(Event::KittyFrozen { kitty_id });
+
+            Ok(())
+        }
+
+        /// 管理员解冻kitty
+        #[pallet::call_index(26)]
+        #[pallet::weight(10_026 + T::DbWeight::get().writes(1).ref_time())]
+        pub fn thaw_kitty(origin: OriginFor<T>, kitty_id: KittyId) -> DispatchResult {
+            T::ForceOrigin::ensure_origin(origin)?;
+
+            ensure!(FrozenKitties::<T, I>::contains_key(kitty_id), Error::<T, I>::NotFrozen);
+            FrozenKitties::<T, I>::remove(kitty_id);
+
+            Self::deposit_event(Event::KittyThawed { kitty_id });
+
+            Ok(())
+        }
+
+        /// root为指定账户创建灵魂绑定的kitty，无需支付
+        #[pallet::call_index(27)]
+        #[pallet::weight(10_027 + T::DbWeight::get().writes(2).ref_time())]
+        pub fn mint_soulbound(origin: OriginFor<T>, owner: T::AccountId, name: [u8; 8]) -> DispatchResult {
+            ensure_root(origin)?;
+
+            let kitty_id = Self::next_mint_id()?;
+            let dna = Self::random_value(&owner, kitty_id);
+            let kitty = Kitty { dna, name, soulbound: true, ..Default::default() };
+
+            Kitties::<T, I>::insert(kitty_id, &kitty);
+            Self::insert_owner(kitty_id, &owner);
+
+            Self::deposit_event(Event::KittyCreated { who: owner, kitty_id, kitty });
+
+            Ok(())
+        }
+
+        /// 将多个kitty以一个价格打包出售，任一kitty转移后打包出售失效
+        #[pallet::call_index(28)]
+        #[pallet::weight((10_028 + T::DbWeight::get().writes(1).ref_time()).saturating_mul(kitty_ids.len() as u64))]
+        pub fn list_bundle(origin: OriginFor<T>, kitty_ids: Vec<KittyId>, price: BalanceOf<T, I>) -> DispatchResult {
             let who = ensure_signed(origin)?;
-            // kitty存在
-            ensure!(Kitties::<T>::contains_key(kitty_id),Error::<T>::InvalidKittyId);
-            // 所有权正确
-            ensure!( Self::kitty_owner(kitty_id) == Some(who.clone()),Error::<T>::NotOwner);
-            // 已经在售状态
-            ensure!(!KittyOnSale::<T>::contains_key(kitty_id), Error::<T>::AlreadyOnSale);
-            // 标记在售
-            KittyOnSale::<T>::insert(kitty_id, ());
 
-            Self::deposit_event(Event::KittyOnSale { who, kitty_id });
+            ensure!(kitty_ids.len() >= 2, Error::<T, I>::InvalidBundle);
+            let kitty_ids = BoundedVec::<KittyId, T::MaxBundleSize>::try_from(kitty_ids)
+                .map_err(|_| Error::<T, I>::InvalidBundle)?;
+
+            let bundle_id = NextBundleId::<T, I>::get();
+            NextBundleId::<T, I>::put(bundle_id.checked_add(1).ok_or(Error::<T, I>::InvalidBundle)?);
+
+            for &kitty_id in kitty_ids.iter() {
+                ensure!(Kitties::<T, I>::contains_key(kitty_id), Error::<T, I>::InvalidKittyId);
+                ensure!(Self::kitty_owner(kitty_id) == Some(who.clone()), Error::<T, I>::NotOwner);
+                // 已单独在售或已在其他打包中，重复的kitty同样会在此失败
+                ensure!(!Self::is_on_sale(kitty_id), Error::<T, I>::AlreadyOnSale);
+                ensure!(!KittyBundle::<T, I>::contains_key(kitty_id), Error::<T, I>::AlreadyOnSale);
+                Self::ensure_unlocked(kitty_id)?;
+                Self::ensure_transferable(kitty_id)?;
+                KittyBundle::<T, I>::insert(kitty_id, bundle_id);
+            }
+            Bundles::<T, I>::insert(bundle_id, (&who, &kitty_ids, price));
+
+            Self::deposit_event(Event::BundleListed { who, bundle_id, kitty_ids, price });
 
             Ok(())
         }
 
-        #[pallet::call_index(4)]
-        #[pallet::weight(10_004 + T::DbWeight::get().writes(1).ref_time())]
-        pub fn buy(origin: OriginFor<T>, kitty_id: KittyId) -> DispatchResult {
+        /// 购买打包出售的全部kitty
+        #[pallet::call_index(29)]
+        #[pallet::weight((10_029 + T::DbWeight::get().writes(4).ref_time()).saturating_mul(T::MaxBundleSize::get() as u64))]
+        pub fn buy_bundle(origin: OriginFor<T>, bundle_id: BundleId) -> DispatchResult {
             let who = ensure_signed(origin)?;
 
-            ensure!(Kitties::<T>::contains_key(kitty_id), Error::<T>::InvalidKittyId);
-            let owner = Self::kitty_owner(kitty_id).ok_or(Error::<T>::NoOwner)?;
+            let (seller, kitty_ids, price) = Self::remove_bundle(bundle_id).ok_or(Error::<T, I>::NoBundle)?;
+            ensure!(seller != who, Error::<T, I>::AlreadyOwned);
 
-            ensure!(owner != who, Error::<T>::AlreadyOwned);
-            ensure!(KittyOnSale::<T>::contains_key(kitty_id), Error::<T>::NotOnSale);
+            T::Currency::transfer(&who, &seller, price, ExistenceRequirement::KeepAlive)?;
 
-            let price = T::KittyPrice::get();
-            // 质押
-            // T::Currency::reserve(&who, price)?;
-            // 解除质押
-            // T::Currency::unreserve(&owner, price);
-            // 转移
-            T::Currency::transfer(&who, &owner,
-                                  price, ExistenceRequirement::KeepAlive)?;
+            for &kitty_id in kitty_ids.iter() {
+                ensure!(Self::kitty_owner(kitty_id) == Some(seller.clone()), Error::<T, I>::NoOwner);
+                Self::ensure_unlocked(kitty_id)?;
+                Self::ensure_transferable(kitty_id)?;
+                Self::set_owner(kitty_id, &who);
+            }
 
-            KittyOwner::<T>::insert(kitty_id, &who);
-            KittyOnSale::<T>::remove(kitty_id);
+            Self::deposit_event(Event::BundleBought { who, bundle_id });
 
-            Self::deposit_event(Event::KittyBought { who, kitty_id });
+            Ok(())
+        }
+
+        /// 卖家撤销打包出售
+        #[pallet::call_index(30)]
+        #[pallet::weight((10_030 + T::DbWeight::get().writes(1).ref_time()).saturating_mul(T::MaxBundleSize::get() as u64))]
+        pub fn cancel_bundle(origin: OriginFor<T>, bundle_id: BundleId) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+
+            let (seller, _, _) = Self::bundles(bundle_id).ok_or(Error::<T, I>::NoBundle)?;
+            ensure!(seller == who, Error::<T, I>::NotOwner);
+            Self::remove_bundle(bundle_id);
+
+            Self::deposit_event(Event::BundleCancelled { who, bundle_id });
 
             Ok(())
         }
-    }
 
-    impl<T: Config> Pallet<T> {
-        /// 返回一个kittyId，并+1后保存为下一个kittyId
-        fn get_next_id() -> Result<KittyId, DispatchError> {
-            NextKittyId::<T>::try_mutate(|next_id| -> Result<KittyId, DispatchError> {
-                // 读取当前的 此时完成了copy
-                let current_id = *next_id;
-                // 更新下一个id，可能超出u32的范围，溢出则抛出Error
-                *next_id = next_id.checked_add(1).ok_or::<DispatchError>(Error::<T>::InvalidKittyId.into())?;
-                Ok(current_id)
-            })
+        /// 登记kitty当前的名称，登记后其他kitty不能再登记该名称
+        #[pallet::call_index(31)]
+        #[pallet::weight(10_031 + T::DbWeight::get().writes(1).ref_time())]
+        pub fn claim_name(origin: OriginFor<T>, kitty_id: KittyId) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+
+            let kitty = Self::kitties(kitty_id).ok_or(Error::<T, I>::InvalidKittyId)?;
+            ensure!(Self::kitty_owner(kitty_id) == Some(who.clone()), Error::<T, I>::NotOwner);
+            ensure!(!NameToKitty::<T, I>::contains_key(kitty.name), Error::<T, I>::NameTaken);
+            NameToKitty::<T, I>::insert(kitty.name, kitty_id);
+
+            Self::deposit_event(Event::NameClaimed { who, kitty_id, name: kitty.name });
+
+            Ok(())
         }
-        /// 生成一个随机数
-        fn random_value(sender: &T::AccountId) -> [u8; 16] {
-            // 多个参数，确保payload唯一
-            let payload = (
-                T::Randomness::random_seed(),
-                &sender,
-                <frame_system::Pallet<T>>::extrinsic_index(),
+
+        /// 释放kitty登记的名称
+        #[pallet::call_index(32)]
+        #[pallet::weight(10_032 + T::DbWeight::get().writes(1).ref_time())]
+        pub fn release_name(origin: OriginFor<T>, kitty_id: KittyId) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+
+            ensure!(Kitties::<T, I>::contains_key(kitty_id), Error::<T, I>::InvalidKittyId);
+            ensure!(Self::kitty_owner(kitty_id) == Some(who), Error::<T, I>::NotOwner);
+            ensure!(Self::unregister_name(kitty_id), Error::<T, I>::NameNotClaimed);
+
+            Ok(())
+        }
+
+        /// kitty改名，已登记的名称随之变更为新名称
+        #[pallet::call_index(33)]
+        #[pallet::weight(10_033 + T::DbWeight::get().writes(3).ref_time())]
+        pub fn rename(origin: OriginFor<T>, kitty_id: KittyId, name: [u8; 8]) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+
+            let mut kitty = Self::kitties(kitty_id).ok_or(Error::<T, I>::InvalidKittyId)?;
+            ensure!(Self::kitty_owner(kitty_id) == Some(who.clone()), Error::<T, I>::NotOwner);
+            Self::ensure_not_frozen(kitty_id)?;
+
+            if Self::name_to_kitty(kitty.name) == Some(kitty_id) {
+                ensure!(Self::name_to_kitty(name).map_or(true, |id| id == kitty_id), Error::<T, I>::NameTaken);
+                Self::unregister_name(kitty_id);
+                NameToKitty::<T, I>::insert(name, kitty_id);
+                Self::deposit_event(Event::NameClaimed { who: who.clone(), kitty_id, name });
+            }
+            kitty.name = name;
+            Kitties::<T, I>::insert(kitty_id, &kitty);
+
+            Self::deposit_event(Event::KittyRenamed { who, kitty_id, name });
+
+            Ok(())
+        }
+
+        /// 按卖家在链下签名的订单购买kitty，签名者必须是kitty当前的owner
+        #[pallet::call_index(34)]
+        #[pallet::weight(10_034 + T::DbWeight::get().writes(5).ref_time())]
+        pub fn fill_order(
+            origin: OriginFor<T>,
+            order: Order<BalanceOf<T, I>, T::BlockNumber>,
+            signature: T::OffchainSignature,
+        ) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+
+            ensure!(<frame_system::Pallet<T>>::block_number() < order.expires_at, Error::<T, I>::OrderExpired);
+            ensure!(Kitties::<T, I>::contains_key(order.kitty_id), Error::<T, I>::InvalidKittyId);
+            let seller = Self::kitty_owner(order.kitty_id).ok_or(Error::<T, I>::NoOwner)?;
+            ensure!(seller != who, Error::<T, I>::AlreadyOwned);
+
+            ensure!(order.nonce >= Self::min_order_nonce(&seller), Error::<T, I>::OrderUsed);
+            ensure!(!UsedOrders::<T, I>::contains_key(&seller, order.nonce), Error::<T, I>::OrderUsed);
+            ensure!(
+                signature.verify(&Self::order_payload(&order)[..], &seller),
+                Error::<T, I>::InvalidSignature
             );
-            // 用blake2_128确保长度match
-            payload.using_encoded(blake2_128)
+            Self::ensure_unlocked(order.kitty_id)?;
+            Self::ensure_transferable(order.kitty_id)?;
+
+            T::Currency::transfer(&who, &seller, order.price, ExistenceRequirement::KeepAlive)?;
+
+            UsedOrders::<T, I>::insert(&seller, order.nonce, ());
+            Self::set_owner(order.kitty_id, &who);
+            KittyOnSale::<T, I>::remove(order.kitty_id);
+
+            Self::deposit_event(Event::OrderFilled {
+                who,
+                seller,
+                kitty_id: order.kitty_id,
+                price: order.price,
+                nonce: order.nonce,
+            });
+
+            Ok(())
         }
-        ///
-        fn get_account_id() -> T::AccountId {
-            T::PalletId::get().into_account_truncating()
+
+        /// 撤销序号不大于nonce的全部链下订单
+        #[pallet::call_index(35)]
+        #[pallet::weight(10_035 + T::DbWeight::get().writes(1).ref_time())]
+        pub fn cancel_orders_up_to(origin: OriginFor<T>, nonce: u64) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+
+            let min_nonce = nonce.checked_add(1).ok_or(Error::<T, I>::InvalidNonce)?;
+            ensure!(min_nonce > Self::min_order_nonce(&who), Error::<T, I>::InvalidNonce);
+            MinOrderNonce::<T, I>::insert(&who, min_nonce);
+
+            Self::deposit_event(Event::OrdersCancelled { who, min_nonce });
+
+            Ok(())
+        }
+
+        /// 用kitty向其他账户的kitty发起挑战并押注，对方接受后才进行战斗
+        #[pallet::call_index(36)]
+        #[pallet::weight(10_036 + T::DbWeight::get().writes(2).ref_time())]
+        pub fn challenge(origin: OriginFor<T>, kitty_id: KittyId, opponent_id: KittyId) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+
+            ensure!(Kitties::<T, I>::contains_key(kitty_id), Error::<T, I>::InvalidKittyId);
+            ensure!(Kitties::<T, I>::contains_key(opponent_id), Error::<T, I>::InvalidKittyId);
+            Self::ensure_can_use(&who, kitty_id)?;
+            let opponent_owner = Self::kitty_owner(opponent_id).ok_or(Error::<T, I>::NoOwner)?;
+            ensure!(opponent_owner != who, Error::<T, I>::CanNotBattleSelf);
+            ensure!(!BattleChallenges::<T, I>::contains_key(kitty_id), Error::<T, I>::ChallengeExists);
+            Self::ensure_can_battle(kitty_id)?;
+            Self::ensure_can_battle(opponent_id)?;
+
+            T::Currency::transfer(&who, &Self::get_account_id(),
+                                  T::BattleStake::get(), ExistenceRequirement::KeepAlive)?;
+            BattleChallenges::<T, I>::insert(kitty_id, (who.clone(), opponent_id, None));
+
+            Self::deposit_event(Event::ChallengeIssued { who, kitty_id, opponent_id });
+
+            Ok(())
+        }
+
+        /// 对方接受挑战并押注，战斗使用之后区块的随机数，双方在提交时都无法预知结果
+        #[pallet::call_index(52)]
+        #[pallet::weight(10_052 + T::DbWeight::get().writes(5).ref_time())]
+        pub fn accept_challenge(origin: OriginFor<T>, kitty_id: KittyId) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+
+            let (challenger, opponent_id, accepted) = Self::battle_challenge(kitty_id).ok_or(Error::<T, I>::NoChallenge)?;
+            ensure!(accepted.is_none(), Error::<T, I>::ChallengeAlreadyAccepted);
+            Self::ensure_can_use(&who, opponent_id)?;
+            ensure!(who != challenger, Error::<T, I>::CanNotBattleSelf);
+            Self::ensure_can_battle(kitty_id)?;
+            Self::ensure_can_battle(opponent_id)?;
+
+            let fight_at = <frame_system::Pallet<T>>::block_number()
+                .saturating_add(T::BreedRevealDelay::get().max(One::one()));
+            RevealQueue::<T, I>::try_append(fight_at, kitty_id)
+                .map_err(|_| Error::<T, I>::TooManyExpiries)?;
+            T::Currency::transfer(&who, &Self::get_account_id(),
+                                  T::BattleStake::get(), ExistenceRequirement::KeepAlive)?;
+
+            // 胜负双方都进入冷却，战斗进行前也不能参加其他战斗
+            let cooldown_until = fight_at.saturating_add(T::BattleCooldown::get());
+            BattleCooldowns::<T, I>::insert(kitty_id, cooldown_until);
+            BattleCooldowns::<T, I>::insert(opponent_id, cooldown_until);
+            BattleChallenges::<T, I>::insert(kitty_id, (challenger, opponent_id, Some((who.clone(), fight_at))));
+
+            Self::deposit_event(Event::ChallengeAccepted { who, kitty_id, opponent_id, fight_at });
+
+            Ok(())
+        }
+
+        /// 挑战方撤销尚未被接受的挑战，退还押注
+        #[pallet::call_index(53)]
+        #[pallet::weight(10_053 + T::DbWeight::get().writes(1).ref_time())]
+        pub fn cancel_challenge(origin: OriginFor<T>, kitty_id: KittyId) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+
+            let (challenger, _, accepted) = Self::battle_challenge(kitty_id).ok_or(Error::<T, I>::NoChallenge)?;
+            ensure!(challenger == who, Error::<T, I>::NotOwner);
+            ensure!(accepted.is_none(), Error::<T, I>::ChallengeAlreadyAccepted);
+
+            Self::cancel_battle(kitty_id);
+
+            Ok(())
+        }
+
+        /// 喂养kitty获得经验，费用支付给pallet账户
+        #[pallet::call_index(37)]
+        #[pallet::weight(10_037 + T::DbWeight::get().writes(2).ref_time())]
+        pub fn feed(origin: OriginFor<T>, kitty_id: KittyId, times: u32) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+
+            ensure!(times > 0, Error::<T, I>::InvalidFeedCount);
+            let mut kitty = Self::kitties(kitty_id).ok_or(Error::<T, I>::InvalidKittyId)?;
+            Self::ensure_can_use(&who, kitty_id)?;
+            Self::ensure_not_frozen(kitty_id)?;
+
+            let price = T::FeedPrice::get().saturating_mul(times.into());
+            T::Currency::transfer(&who, &Self::get_account_id(), price, ExistenceRequirement::KeepAlive)?;
+
+            kitty.experience = kitty.experience.saturating_add(T::XpPerFeed::get().saturating_mul(times));
+            kitty.level = Self::level_of(kitty.experience);
+            Kitties::<T, I>::insert(kitty_id, &kitty);
+
+            Self::deposit_event(Event::KittyFed { who, kitty_id, experience: kitty.experience, level: kitty.level });
+
+            Ok(())
+        }
+
+        /// 达到变异等级后支付并提交变异，dna在之后区块由随机数改变，每只kitty只能变异一次
+        #[pallet::call_index(38)]
+        #[pallet::weight(10_038 + T::DbWeight::get().writes(3).ref_time())]
+        pub fn mutate(origin: OriginFor<T>, kitty_id: KittyId) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+
+            let kitty = Self::kitties(kitty_id).ok_or(Error::<T, I>::InvalidKittyId)?;
+            ensure!(Self::kitty_owner(kitty_id) == Some(who.clone()), Error::<T, I>::NotOwner);
+            Self::ensure_not_frozen(kitty_id)?;
+            ensure!(kitty.level >= T::MutationLevel::get(), Error::<T, I>::LevelTooLow);
+            ensure!(!kitty.mutated, Error::<T, I>::AlreadyMutated);
+            ensure!(!MutationCommits::<T, I>::contains_key(kitty_id), Error::<T, I>::MutationPending);
+            // 变异可能改变稀有度，质押中不能变异，避免按新稀有度结算整个质押期的奖励
+            ensure!(!StakedKitties::<T, I>::contains_key(kitty_id), Error::<T, I>::KittyLocked);
+
+            T::Currency::transfer(&who, &Self::get_account_id(),
+                                  T::MutationPrice::get(), ExistenceRequirement::KeepAlive)?;
+
+            // 与两阶段繁衍相同，至少延迟一个区块，提交时无法预知变异结果
+            let reveal_at = <frame_system::Pallet<T>>::block_number()
+                .saturating_add(T::BreedRevealDelay::get().max(One::one()));
+            RevealQueue::<T, I>::try_append(reveal_at, kitty_id)
+                .map_err(|_| Error::<T, I>::TooManyExpiries)?;
+            MutationCommits::<T, I>::insert(kitty_id, (&who, reveal_at));
+
+            Self::deposit_event(Event::MutationCommitted { who, kitty_id, reveal_at });
+
+            Ok(())
+        }
+
+        /// 质押kitty，质押期间不能转移和出售
+        #[pallet::call_index(39)]
+        #[pallet::weight(10_039 + T::DbWeight::get().writes(1).ref_time())]
+        pub fn stake(origin: OriginFor<T>, kitty_id: KittyId) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+
+            ensure!(Kitties::<T, I>::contains_key(kitty_id), Error::<T, I>::InvalidKittyId);
+            ensure!(Self::kitty_owner(kitty_id) == Some(who.clone()), Error::<T, I>::NotOwner);
+            ensure!(!Self::is_on_sale(kitty_id), Error::<T, I>::AlreadyOnSale);
+            ensure!(!KittyBundle::<T, I>::contains_key(kitty_id), Error::<T, I>::AlreadyOnSale);
+            // 已经质押的kitty同样在此失败
+            Self::ensure_unlocked(kitty_id)?;
+
+            StakedKitties::<T, I>::insert(kitty_id, (&who, <frame_system::Pallet<T>>::block_number()));
+
+            Self::deposit_event(Event::KittyStaked { who, kitty_id });
+
+            Ok(())
+        }
+
+        /// 解除质押，同时尽力结算未领取的奖励
+        #[pallet::call_index(40)]
+        #[pallet::weight(10_040 + T::DbWeight::get().writes(2).ref_time())]
+        pub fn unstake(origin: OriginFor<T>, kitty_id: KittyId) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+
+            let (staker, _) = Self::staked_kitty(kitty_id).ok_or(Error::<T, I>::NotStaked)?;
+            ensure!(staker == who, Error::<T, I>::NotOwner);
+
+            // 奖励不足不能影响取回kitty
+            let reward = Self::pending_reward(kitty_id);
+            let reward = T::Currency::transfer(&Self::get_account_id(), &who, reward, ExistenceRequirement::KeepAlive)
+                .ok()
+                .map(|_| reward);
+            StakedKitties::<T, I>::remove(kitty_id);
+
+            Self::deposit_event(Event::KittyUnstaked { who, kitty_id, reward });
+
+            Ok(())
+        }
+
+        /// 领取质押奖励，奖励按区块数和稀有度计算
+        #[pallet::call_index(41)]
+        #[pallet::weight(10_041 + T::DbWeight::get().writes(2).ref_time())]
+        pub fn claim_staking_reward(origin: OriginFor<T>, kitty_id: KittyId) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+
+            let (staker, _) = Self::staked_kitty(kitty_id).ok_or(Error::<T, I>::NotStaked)?;
+            ensure!(staker == who, Error::<T, I>::NotOwner);
+
+            let reward = Self::pending_reward(kitty_id);
+            T::Currency::transfer(&Self::get_account_id(), &who, reward, ExistenceRequirement::KeepAlive)?;
+            StakedKitties::<T, I>::insert(kitty_id, (&who, <frame_system::Pallet<T>>::block_number()));
+
+            Self::deposit_event(Event::StakingRewardClaimed { who, kitty_id, reward });
+
+            Ok(())
+        }
+
+        /// 以kitty抵押请求借款，金额不超过kitty价格乘以抵押率，请求期间kitty被锁定
+        #[pallet::call_index(42)]
+        #[pallet::weight(10_042 + T::DbWeight::get().writes(1).ref_time())]
+        pub fn request_loan(origin: OriginFor<T>, kitty_id: KittyId, amount: BalanceOf<T, I>, duration: T::BlockNumber) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+
+            ensure!(Kitties::<T, I>::contains_key(kitty_id), Error::<T, I>::InvalidKittyId);
+            ensure!(Self::kitty_owner(kitty_id) == Some(who.clone()), Error::<T, I>::NotOwner);
+            ensure!(!amount.is_zero(), Error::<T, I>::InvalidLoanAmount);
+            ensure!(amount <= T::LoanToValue::get() * T::KittyPrice::get(), Error::<T, I>::InvalidLoanAmount);
+            ensure!(!duration.is_zero(), Error::<T, I>::InvalidDuration);
+            ensure!(!Self::is_on_sale(kitty_id), Error::<T, I>::AlreadyOnSale);
+            ensure!(!KittyBundle::<T, I>::contains_key(kitty_id), Error::<T, I>::AlreadyOnSale);
+            Self::ensure_unlocked(kitty_id)?;
+            Self::ensure_transferable(kitty_id)?;
+
+            LoanRequests::<T, I>::insert(kitty_id, (&who, amount, duration));
+
+            Self::deposit_event(Event::LoanRequested { who, kitty_id, amount, duration });
+
+            Ok(())
+        }
+
+        /// 借款人撤销未放款的借款请求
+        #[pallet::call_index(43)]
+        #[pallet::weight(10_043 + T::DbWeight::get().writes(1).ref_time())]
+        pub fn cancel_loan_request(origin: OriginFor<T>, kitty_id: KittyId) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+
+            let (borrower, _, _) = Self::loan_request(kitty_id).ok_or(Error::<T, I>::NoLoanRequest)?;
+            ensure!(borrower == who, Error::<T, I>::NotOwner);
+            LoanRequests::<T, I>::remove(kitty_id);
+
+            Self::deposit_event(Event::LoanRequestCancelled { who, kitty_id });
+
+            Ok(())
+        }
+
+        /// 出借人放款，kitty锁定至还款或到期
+        #[pallet::call_index(44)]
+        #[pallet::weight(10_044 + T::DbWeight::get().writes(3).ref_time())]
+        pub fn fund_loan(origin: OriginFor<T>, kitty_id: KittyId) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+
+            let (borrower, amount, duration) = LoanRequests::<T, I>::take(kitty_id).ok_or(Error::<T, I>::NoLoanRequest)?;
+            ensure!(borrower != who, Error::<T, I>::CanNotTransferToSelf);
+            ensure!(Self::kitty_owner(kitty_id) == Some(borrower.clone()), Error::<T, I>::NoOwner);
+            // 请求之后被冻结或出现其他锁定的kitty不能作为抵押
+            Self::ensure_unlocked(kitty_id)?;
+
+            T::Currency::transfer(&who, &borrower, amount, ExistenceRequirement::KeepAlive)?;
+
+            let deadline = <frame_system::Pallet<T>>::block_number().saturating_add(duration);
+            LoanDeadlines::<T, I>::try_append(deadline, kitty_id)
+                .map_err(|_| Error::<T, I>::TooManyExpiries)?;
+            Loans::<T, I>::insert(kitty_id, (&borrower, &who, amount, deadline));
+
+            Self::deposit_event(Event::LoanFunded { lender: who, borrower, kitty_id, amount, deadline });
+
+            Ok(())
+        }
+
+        /// 借款人在到期前偿还本金和利息，解除kitty的锁定
+        #[pallet::call_index(45)]
+        #[pallet::weight(10_045 + T::DbWeight::get().writes(2).ref_time())]
+        pub fn repay_loan(origin: OriginFor<T>, kitty_id: KittyId) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+
+            let (borrower, lender, principal, deadline) = Loans::<T, I>::take(kitty_id).ok_or(Error::<T, I>::NoLoan)?;
+            ensure!(borrower == who, Error::<T, I>::NotBorrower);
+            // 释放到期队列中的位置
+            LoanDeadlines::<T, I>::mutate(deadline, |kitty_ids| kitty_ids.retain(|id| *id != kitty_id));
+
+            let repayment = principal.saturating_add(T::LoanInterest::get() * principal);
+            T::Currency::transfer(&who, &lender, repayment, ExistenceRequirement::KeepAlive)?;
+
+            Self::deposit_event(Event::LoanRepaid { who, kitty_id, repayment });
+
+            Ok(())
+        }
+
+        /// root创建投票，以当前区块为快照
+        #[pallet::call_index(46)]
+        #[pallet::weight(10_046 + T::DbWeight::get().writes(2).ref_time())]
+        pub fn create_poll(origin: OriginFor<T>, options: Vec<[u8; 8]>, end: T::BlockNumber) -> DispatchResult {
+            ensure_root(origin)?;
+
+            ensure!(options.len() >= 2, Error::<T, I>::InvalidPoll);
+            let options = BoundedVec::<[u8; 8], T::MaxPollOptions>::try_from(options)
+                .map_err(|_| Error::<T, I>::InvalidPoll)?;
+            ensure!(end > <frame_system::Pallet<T>>::block_number(), Error::<T, I>::InvalidPoll);
+
+            let poll_id = NextPollId::<T, I>::get();
+            NextPollId::<T, I>::put(poll_id.checked_add(1).ok_or(Error::<T, I>::InvalidPoll)?);
+            let snapshot = <frame_system::Pallet<T>>::block_number();
+            let tally = BoundedVec::<u32, T::MaxPollOptions>::truncate_from(options.iter().map(|_| 0).collect::<Vec<_>>());
+            Polls::<T, I>::insert(poll_id, (&options, tally, end, snapshot, false));
+
+            Self::deposit_event(Event::PollCreated { poll_id, options, end, snapshot });
+
+            Ok(())
+        }
+
+        /// 以快照前已持有的kitty投票，每只kitty一票；快照后转移或生成的kitty不能投票
+        #[pallet::call_index(47)]
+        #[pallet::weight((10_047 + T::DbWeight::get().writes(1).ref_time()).saturating_mul(kitty_ids.len() as u64))]
+        pub fn vote(origin: OriginFor<T>, poll_id: PollId, kitty_ids: Vec<KittyId>, option: u32) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+
+            ensure!(!kitty_ids.is_empty(), Error::<T, I>::EmptyVote);
+            ensure!(kitty_ids.len() as u32 <= T::MaxBatchSize::get(), Error::<T, I>::BatchTooLarge);
+            let (options, mut tally, end, snapshot, closed) = Self::polls(poll_id).ok_or(Error::<T, I>::NoPoll)?;
+            ensure!(!closed, Error::<T, I>::PollClosed);
+            ensure!(<frame_system::Pallet<T>>::block_number() < end, Error::<T, I>::PollEnded);
+            ensure!((option as usize) < options.len(), Error::<T, I>::InvalidOption);
+
+            for &kitty_id in kitty_ids.iter() {
+                ensure!(Self::kitty_owner(kitty_id) == Some(who.clone()), Error::<T, I>::NotOwner);
+                // 快照时的持有人仍持有该kitty
+                ensure!(Self::kitty_acquired_at(kitty_id) < snapshot, Error::<T, I>::NotInSnapshot);
+                Self::ensure_not_frozen(kitty_id)?;
+                // 同一调用中重复的kitty同样会在此失败
+                ensure!(!PollVotes::<T, I>::contains_key(poll_id, kitty_id), Error::<T, I>::AlreadyVoted);
+                PollVotes::<T, I>::insert(poll_id, kitty_id, option);
+            }
+            let votes = kitty_ids.len() as u32;
+            if let Some(count) = tally.get_mut(option as usize) {
+                *count = count.saturating_add(votes);
+            }
+            Polls::<T, I>::insert(poll_id, (options, tally, end, snapshot, closed));
+
+            Self::deposit_event(Event::Voted { who, poll_id, option, votes });
+
+            Ok(())
+        }
+
+        /// 到达结束区块后，任何人都可以结束投票并公布结果
+        #[pallet::call_index(48)]
+        #[pallet::weight(10_048 + T::DbWeight::get().writes(1).ref_time())]
+        pub fn close_poll(origin: OriginFor<T>, poll_id: PollId) -> DispatchResult {
+            ensure_signed(origin)?;
+
+            let (options, tally, end, snapshot, closed) = Self::polls(poll_id).ok_or(Error::<T, I>::NoPoll)?;
+            ensure!(!closed, Error::<T, I>::PollClosed);
+            ensure!(<frame_system::Pallet<T>>::block_number() >= end, Error::<T, I>::PollNotEnded);
+
+            let winner = Self::poll_winner(&tally);
+            Polls::<T, I>::insert(poll_id, (options, &tally, end, snapshot, true));
+
+            Self::deposit_event(Event::PollClosed { poll_id, tally, winner });
+
+            Ok(())
+        }
+
+        /// root设置盲盒的价格和稀有度权重表
+        #[pallet::call_index(49)]
+        #[pallet::weight(10_049 + T::DbWeight::get().writes(1).ref_time())]
+        pub fn set_mystery_box(origin: OriginFor<T>, box_id: MysteryBoxId, price: BalanceOf<T, I>, table: Vec<(u8, u32)>) -> DispatchResult {
+            ensure_root(origin)?;
+
+            let table = RarityTable::try_from(table).map_err(|_| Error::<T, I>::InvalidRarityTable)?;
+            let mut total: u32 = 0;
+            for (index, &(rarity, weight)) in table.iter().enumerate() {
+                ensure!(Self::rarity_range(rarity).is_some() && weight > 0, Error::<T, I>::InvalidRarityTable);
+                ensure!(table[..index].iter().all(|(other, _)| *other != rarity), Error::<T, I>::InvalidRarityTable);
+                total = total.checked_add(weight).ok_or(Error::<T, I>::InvalidRarityTable)?;
+            }
+            ensure!(total > 0, Error::<T, I>::InvalidRarityTable);
+
+            MysteryBoxes::<T, I>::insert(box_id, (price, &table));
+
+            Self::deposit_event(Event::MysteryBoxSet { box_id, price, table });
+
+            Ok(())
+        }
+
+        /// root移除盲盒
+        #[pallet::call_index(50)]
+        #[pallet::weight(10_050 + T::DbWeight::get().writes(1).ref_time())]
+        pub fn remove_mystery_box(origin: OriginFor<T>, box_id: MysteryBoxId) -> DispatchResult {
+            ensure_root(origin)?;
+
+            ensure!(MysteryBoxes::<T, I>::contains_key(box_id), Error::<T, I>::NoMysteryBox);
+            MysteryBoxes::<T, I>::remove(box_id);
+
+            Self::deposit_event(Event::MysteryBoxRemoved { box_id });
+
+            Ok(())
+        }
+
+        /// 购买盲盒：支付价格并占用kittyId，稀有度由之后区块的随机数决定，购买时无法预知
+        #[pallet::call_index(51)]
+        #[pallet::weight(10_051 + T::DbWeight::get().writes(3).ref_time())]
+        pub fn open_mystery_box(origin: OriginFor<T>, box_id: MysteryBoxId, name: [u8; 8]) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+
+            let (price, table) = Self::mystery_box(box_id).ok_or(Error::<T, I>::NoMysteryBox)?;
+            let kitty_id = Self::reserve_mint(&who)?;
+            T::Currency::transfer(&who, &Self::get_account_id(),
+                                  price, ExistenceRequirement::KeepAlive)?;
+
+            let reveal_at = <frame_system::Pallet<T>>::block_number()
+                .saturating_add(T::BreedRevealDelay::get().max(One::one()));
+            RevealQueue::<T, I>::try_append(reveal_at, kitty_id)
+                .map_err(|_| Error::<T, I>::TooManyExpiries)?;
+            // 按购买时的权重表开启，之后root修改盲盒不影响已购买的
+            MysteryBoxCommits::<T, I>::insert(kitty_id, (&who, box_id, table, name, reveal_at));
+
+            Self::deposit_event(Event::MysteryBoxCommitted { who, box_id, kitty_id, reveal_at });
+
+            Ok(())
+        }
+
+        /// 到达reveal区块后，任何人都可以开启盲盒：按权重表选出稀有度，再生成该稀有度范围内的dna
+        #[pallet::call_index(54)]
+        #[pallet::weight(10_054 + T::DbWeight::get().writes(4).ref_time())]
+        pub fn reveal_mystery_box(origin: OriginFor<T>, kitty_id: KittyId) -> DispatchResult {
+            ensure_signed(origin)?;
+
+            let (who, box_id, table, name, reveal_at) = Self::mystery_box_commit(kitty_id).ok_or(Error::<T, I>::NoMysteryBoxCommit)?;
+            ensure!(<frame_system::Pallet<T>>::block_number() >= reveal_at, Error::<T, I>::MysteryBoxNotReady);
+            let seed = Self::reveal_seed(kitty_id).ok_or(Error::<T, I>::MysteryBoxNotReady)?;
+            let rarity = Self::pick_rarity(&table, seed).ok_or(Error::<T, I>::InvalidRarityTable)?;
+            let dna = Self::rarity_dna(rarity, seed).ok_or(Error::<T, I>::InvalidRarityTable)?;
+            let kitty = Kitty { dna, name, soulbound: false, ..Default::default() };
+
+            MysteryBoxCommits::<T, I>::remove(kitty_id);
+            RevealSeeds::<T, I>::remove(kitty_id);
+            Kitties::<T, I>::insert(kitty_id, &kitty);
+            Self::insert_owner(kitty_id, &who);
+
+            Self::deposit_event(Event::KittyCreated { who: who.clone(), kitty_id, kitty });
+            Self::deposit_event(Event::MysteryBoxOpened { who, box_id, kitty_id, rarity });
+
+            Ok(())
+        }
+
+        /// 转移kitty
+        #[pallet::call_index(2)]
+        #[pallet::weight(10_002 + T::DbWeight::get().writes(1).ref_time())]
+        pub fn transfer(origin: OriginFor<T>, recipient: T::AccountId, kitty_id: KittyId) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+
+            Self::do_transfer(&who, recipient, kitty_id)
+        }
+
+        /// 标记可售
+        #[pallet::call_index(3)]
+        #[pallet::weight(10_003 + T::DbWeight::get().writes(1).ref_time())]
+        pub fn sale(
+            origin: OriginFor<T>,
+            kitty_id: KittyId,
+            expires_at: Option<T::BlockNumber>,
+            buyer: Option<T::AccountId>,
+            asset_price: Option<(AssetIdOf<T, I>, AssetBalanceOf<T, I>)>,
+        ) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+
+            Self::do_sale(&who, kitty_id, expires_at, buyer, asset_price)
+        }
+
+        #[pallet::call_index(4)]
+        #[pallet::weight(10_004 + T::DbWeight::get().writes(1).ref_time())]
+        pub fn buy(origin: OriginFor<T>, kitty_id: KittyId) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+
+            ensure!(Kitties::<T, I>::contains_key(kitty_id), Error::<T, I>::InvalidKittyId);
+            let owner = Self::kitty_owner(kitty_id).ok_or(Error::<T, I>::NoOwner)?;
+
+            ensure!(owner != who, Error::<T, I>::AlreadyOwned);
+            let listing = Self::kitty_on_sale(kitty_id).ok_or(Error::<T, I>::NotOnSale)?;
+            // 过期但尚未清理的在售同样不能购买
+            if let Some(expires_at) = listing.expires_at {
+                ensure!(<frame_system::Pallet<T>>::block_number() < expires_at, Error::<T, I>::ListingExpired);
+            }
+            // 私下出售只有指定买家能购买
+            if let Some(buyer) = listing.buyer {
+                ensure!(buyer == who, Error::<T, I>::NotDesignatedBuyer);
+            }
+            Self::ensure_unlocked(kitty_id)?;
+            Self::ensure_transferable(kitty_id)?;
+
+            match listing.asset_price {
+                // 以指定资产支付
+                Some((asset_id, amount)) => {
+                    T::Assets::transfer(asset_id, &who, &owner, amount, true)?;
+                }
+                None => {
+                    let price = T::KittyPrice::get();
+                    // 质押
+                    // T::Currency::reserve(&who, price)?;
+                    // 解除质押
+                    // T::Currency::unreserve(&owner, price);
+                    // 转移
+                    T::Currency::transfer(&who, &owner,
+                                          price, ExistenceRequirement::KeepAlive)?;
+                }
+            }
+
+            Self::set_owner(kitty_id, &who);
+            KittyOnSale::<T, I>::remove(kitty_id);
+
+            Self::deposit_event(Event::KittyBought { who, kitty_id });
+
+            Ok(())
+        }
+
+        /// 发起转移，需接收人确认后才会变更owner
+        #[pallet::call_index(5)]
+        #[pallet::weight(10_005 + T::DbWeight::get().writes(1).ref_time())]
+        pub fn offer_transfer(origin: OriginFor<T>, kitty_id: KittyId, recipient: T::AccountId) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+
+            ensure!(Kitties::<T, I>::contains_key(kitty_id), Error::<T, I>::InvalidKittyId);
+            ensure!(Self::kitty_owner(kitty_id) == Some(who.clone()), Error::<T, I>::NotOwner);
+            ensure!(recipient != who, Error::<T, I>::CanNotTransferToSelf);
+            // 在售或打包出售中的kitty不能转移
+            ensure!(!Self::is_on_sale(kitty_id), Error::<T, I>::AlreadyOnSale);
+            ensure!(!KittyBundle::<T, I>::contains_key(kitty_id), Error::<T, I>::AlreadyOnSale);
+            // 已有未过期的待接收转移
+            Self::ensure_unlocked(kitty_id)?;
+            Self::ensure_transferable(kitty_id)?;
+
+            let expires_at = <frame_system::Pallet<T>>::block_number()
+                .saturating_add(T::PendingTransferExpiry::get());
+            PendingTransfers::<T, I>::insert(kitty_id, (&recipient, expires_at));
+
+            Self::deposit_event(Event::TransferOffered { who, recipient, kitty_id, expires_at });
+
+            Ok(())
+        }
+
+        /// 接收人确认转移
+        #[pallet::call_index(6)]
+        #[pallet::weight(10_006 + T::DbWeight::get().writes(2).ref_time())]
+        pub fn accept_transfer(origin: OriginFor<T>, kitty_id: KittyId) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+
+            let (recipient, expires_at) = Self::pending_transfer(kitty_id).ok_or(Error::<T, I>::NoPendingTransfer)?;
+            ensure!(recipient == who, Error::<T, I>::NotRecipient);
+            ensure!(<frame_system::Pallet<T>>::block_number() < expires_at, Error::<T, I>::TransferExpired);
+            Self::ensure_not_frozen(kitty_id)?;
+            let owner = Self::kitty_owner(kitty_id).ok_or(Error::<T, I>::NoOwner)?;
+
+            Self::set_owner(kitty_id, &who);
+
+            Self::deposit_event(Event::TransferAccepted { who, from: owner, kitty_id });
+
+            Ok(())
+        }
+
+        /// 接收人拒绝转移
+        #[pallet::call_index(7)]
+        #[pallet::weight(10_007 + T::DbWeight::get().writes(1).ref_time())]
+        pub fn reject_transfer(origin: OriginFor<T>, kitty_id: KittyId) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+
+            let (recipient, _) = Self::pending_transfer(kitty_id).ok_or(Error::<T, I>::NoPendingTransfer)?;
+            ensure!(recipient == who, Error::<T, I>::NotRecipient);
+
+            PendingTransfers::<T, I>::remove(kitty_id);
+
+            Self::deposit_event(Event::TransferRejected { who, kitty_id });
+
+            Ok(())
+        }
+
+        /// Owner撤销转移，已过期的转移也可撤销
+        #[pallet::call_index(8)]
+        #[pallet::weight(10_008 + T::DbWeight::get().writes(1).ref_time())]
+        pub fn cancel_transfer(origin: OriginFor<T>, kitty_id: KittyId) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+
+            ensure!(PendingTransfers::<T, I>::contains_key(kitty_id), Error::<T, I>::NoPendingTransfer);
+            ensure!(Self::kitty_owner(kitty_id) == Some(who.clone()), Error::<T, I>::NotOwner);
+
+            PendingTransfers::<T, I>::remove(kitty_id);
+
+            Self::deposit_event(Event::TransferCancelled { who, kitty_id });
+
+            Ok(())
+        }
+
+        /// 批量创建Kitty，任一失败则全部回滚
+        #[pallet::call_index(9)]
+        #[pallet::weight((10_000 + T::DbWeight::get().writes(2).ref_time()).saturating_mul(names.len() as u64))]
+        pub fn batch_create(origin: OriginFor<T>, names: Vec<[u8; 8]>) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+
+            ensure!(names.len() as u32 <= T::MaxBatchSize::get(), Error::<T, I>::BatchTooLarge);
+            for name in names {
+                Self::do_create_kitty(&who, name, T::KittyPrice::get())?;
+            }
+
+            Ok(())
+        }
+
+        /// 批量转移Kitty，任一失败则全部回滚
+        #[pallet::call_index(10)]
+        #[pallet::weight((10_002 + T::DbWeight::get().writes(2).ref_time()).saturating_mul(transfers.len() as u64))]
+        pub fn batch_transfer(origin: OriginFor<T>, transfers: Vec<(T::AccountId, KittyId)>) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+
+            ensure!(transfers.len() as u32 <= T::MaxBatchSize::get(), Error::<T, I>::BatchTooLarge);
+            for (recipient, kitty_id) in transfers {
+                Self::do_transfer(&who, recipient, kitty_id)?;
+            }
+
+            Ok(())
+        }
+
+        /// 批量标记可售，任一失败则全部回滚
+        #[pallet::call_index(11)]
+        #[pallet::weight((10_003 + T::DbWeight::get().writes(1).ref_time()).saturating_mul(kitty_ids.len() as u64))]
+        pub fn batch_sale(origin: OriginFor<T>, kitty_ids: Vec<KittyId>, expires_at: Option<T::BlockNumber>) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+
+            ensure!(kitty_ids.len() as u32 <= T::MaxBatchSize::get(), Error::<T, I>::BatchTooLarge);
+            for kitty_id in kitty_ids {
+                Self::do_sale(&who, kitty_id, expires_at, None, None)?;
+            }
+
+            Ok(())
+        }
+
+        /// 出租kitty，租借人确认并支付租金后生效，owner保持不变
+        #[pallet::call_index(12)]
+        #[pallet::weight(10_012 + T::DbWeight::get().writes(1).ref_time())]
+        pub fn lend(origin: OriginFor<T>, kitty_id: KittyId, borrower: T::AccountId, duration: T::BlockNumber, fee: BalanceOf<T, I>) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+
+            ensure!(Kitties::<T, I>::contains_key(kitty_id), Error::<T, I>::InvalidKittyId);
+            ensure!(Self::kitty_owner(kitty_id) == Some(who.clone()), Error::<T, I>::NotOwner);
+            ensure!(borrower != who, Error::<T, I>::CanNotTransferToSelf);
+            ensure!(!duration.is_zero(), Error::<T, I>::InvalidDuration);
+            ensure!(!Self::is_on_sale(kitty_id), Error::<T, I>::AlreadyOnSale);
+            ensure!(!KittyBundle::<T, I>::contains_key(kitty_id), Error::<T, I>::AlreadyOnSale);
+            Self::ensure_unlocked(kitty_id)?;
+
+            RentalOffers::<T, I>::insert(kitty_id, (&borrower, duration, fee));
+
+            Self::deposit_event(Event::RentalOffered { who, borrower, kitty_id, duration, fee });
+
+            Ok(())
+        }
+
+        /// 租借人确认租借并支付租金
+        #[pallet::call_index(13)]
+        #[pallet::weight(10_013 + T::DbWeight::get().writes(3).ref_time())]
+        pub fn borrow(origin: OriginFor<T>, kitty_id: KittyId) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+
+            let (borrower, duration, fee) = Self::rental_offer(kitty_id).ok_or(Error::<T, I>::NoRentalOffer)?;
+            ensure!(borrower == who, Error::<T, I>::NotBorrower);
+            Self::ensure_not_frozen(kitty_id)?;
+            let owner = Self::kitty_owner(kitty_id).ok_or(Error::<T, I>::NoOwner)?;
+
+            let expires_at = <frame_system::Pallet<T>>::block_number().saturating_add(duration);
+            RentalExpiries::<T, I>::try_append(expires_at, kitty_id)
+                .map_err(|_| Error::<T, I>::TooManyExpiries)?;
+
+            T::Currency::transfer(&who, &owner, fee, ExistenceRequirement::KeepAlive)?;
+
+            RentalOffers::<T, I>::remove(kitty_id);
+            KittyRentals::<T, I>::insert(kitty_id, (&who, expires_at));
+
+            Self::deposit_event(Event::KittyLent { owner, borrower: who, kitty_id, expires_at });
+
+            Ok(())
+        }
+
+        /// 撤销出租要约
+        #[pallet::call_index(14)]
+        #[pallet::weight(10_014 + T::DbWeight::get().writes(1).ref_time())]
+        pub fn cancel_lend(origin: OriginFor<T>, kitty_id: KittyId) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+
+            ensure!(RentalOffers::<T, I>::contains_key(kitty_id), Error::<T, I>::NoRentalOffer);
+            ensure!(Self::kitty_owner(kitty_id) == Some(who.clone()), Error::<T, I>::NotOwner);
+
+            RentalOffers::<T, I>::remove(kitty_id);
+
+            Self::deposit_event(Event::RentalCancelled { who, kitty_id });
+
+            Ok(())
+        }
+
+        /// 发起kitty交换，可附带补差价，对方确认后原子完成
+        #[pallet::call_index(15)]
+        #[pallet::weight(10_015 + T::DbWeight::get().writes(1).ref_time())]
+        pub fn propose_swap(origin: OriginFor<T>, kitty_id: KittyId, their_kitty_id: KittyId, top_up: Option<BalanceOf<T, I>>) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+
+            ensure!(kitty_id != their_kitty_id, Error::<T, I>::SameKittyId);
+            ensure!(Kitties::<T, I>::contains_key(kitty_id), Error::<T, I>::InvalidKittyId);
+            ensure!(Kitties::<T, I>::contains_key(their_kitty_id), Error::<T, I>::InvalidKittyId);
+            ensure!(Self::kitty_owner(kitty_id) == Some(who.clone()), Error::<T, I>::NotOwner);
+            ensure!(Self::kitty_owner(their_kitty_id) != Some(who.clone()), Error::<T, I>::AlreadyOwned);
+            ensure!(!Self::is_on_sale(kitty_id), Error::<T, I>::AlreadyOnSale);
+            Self::ensure_unlocked(kitty_id)?;
+            Self::ensure_transferable(kitty_id)?;
+            Self::ensure_transferable(their_kitty_id)?;
+
+            let expires_at = <frame_system::Pallet<T>>::block_number()
+                .saturating_add(T::SwapExpiry::get());
+            SwapOrders::<T, I>::insert(kitty_id, (&who, their_kitty_id, top_up, expires_at));
+
+            Self::deposit_event(Event::SwapProposed { who, kitty_id, their_kitty_id, top_up, expires_at });
+
+            Ok(())
+        }
+
+        /// 对方kitty的owner确认交换，kitty_id为发起人的kitty
+        #[pallet::call_index(16)]
+        #[pallet::weight(10_016 + T::DbWeight::get().writes(4).ref_time())]
+        pub fn accept_swap(origin: OriginFor<T>, kitty_id: KittyId) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+
+            let (proposer, their_kitty_id, top_up, expires_at) = Self::swap_order(kitty_id).ok_or(Error::<T, I>::NoSwapOrder)?;
+            ensure!(<frame_system::Pallet<T>>::block_number() < expires_at, Error::<T, I>::SwapExpired);
+            ensure!(Self::kitty_owner(their_kitty_id) == Some(who.clone()), Error::<T, I>::NotOwner);
+            ensure!(Self::kitty_owner(kitty_id) == Some(proposer.clone()), Error::<T, I>::NoOwner);
+            ensure!(!Self::is_on_sale(their_kitty_id), Error::<T, I>::AlreadyOnSale);
+            Self::ensure_not_frozen(kitty_id)?;
+            Self::ensure_unlocked(their_kitty_id)?;
+            Self::ensure_transferable(their_kitty_id)?;
+
+            if let Some(amount) = top_up {
+                T::Currency::transfer(&proposer, &who, amount, ExistenceRequirement::KeepAlive)?;
+            }
+
+            SwapOrders::<T, I>::remove(kitty_id);
+            Self::set_owner(kitty_id, &who);
+            Self::set_owner(their_kitty_id, &proposer);
+
+            Self::deposit_event(Event::KittySwapped { who, kitty_id, proposer, their_kitty_id });
+
+            Ok(())
+        }
+
+        /// 发起人撤销交换要约，已过期的要约也可撤销
+        #[pallet::call_index(17)]
+        #[pallet::weight(10_017 + T::DbWeight::get().writes(1).ref_time())]
+        pub fn cancel_swap(origin: OriginFor<T>, kitty_id: KittyId) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+
+            let (proposer, _, _, _) = Self::swap_order(kitty_id).ok_or(Error::<T, I>::NoSwapOrder)?;
+            ensure!(proposer == who, Error::<T, I>::NotOwner);
+
+            SwapOrders::<T, I>::remove(kitty_id);
+
+            Self::deposit_event(Event::SwapCancelled { who, kitty_id });
+
+            Ok(())
+        }
+    }
+
+    impl<T: Config<I>, I: 'static> Pallet<T, I> {
+        /// 创建Kitty并按price扣除费用
+        fn do_create_kitty(who: &T::AccountId, name: [u8; 8], price: BalanceOf<T, I>) -> DispatchResult {
+            let kitty_id = Self::reserve_mint(who)?;
+            let dna = Self::random_value(who, kitty_id);
+            let kitty = Kitty { dna, name, soulbound: false, ..Default::default() };
+
+            // T::Currency::reserve(who, price)?;
+            T::Currency::transfer(who, &Self::get_account_id(),
+                                  price, ExistenceRequirement::KeepAlive)?;
+
+            Kitties::<T, I>::insert(kitty_id, &kitty);
+            Self::insert_owner(kitty_id, who);
+
+
+            // 发布创建成功事件
+            Self::deposit_event(Event::KittyCreated { who: who.clone(), kitty_id, kitty });
+            // Return a successful DispatchResultWithPostInfo
+            Ok(())
+        }
+        /// 转移kitty
+        fn do_transfer(who: &T::AccountId, recipient: T::AccountId, kitty_id: KittyId) -> DispatchResult {
+            ensure!(Kitties::<T, I>::contains_key(kitty_id),Error::<T, I>::InvalidKittyId);
+
+            ensure!( Self::kitty_owner(kitty_id) == Some(who.clone()),Error::<T, I>::NotOwner);
+
+            ensure!(&recipient != who,Error::<T, I>::CanNotTransferToSelf);
+
+            Self::ensure_unlocked(kitty_id)?;
+            Self::ensure_transferable(kitty_id)?;
+
+            Self::set_owner(kitty_id, &recipient);
+
+            Self::deposit_event(Event::KittyTransferred { who: who.clone(), recipient, kitty_id });
+
+            Ok(())
+        }
+        /// 标记可售
+        fn do_sale(
+            who: &T::AccountId,
+            kitty_id: KittyId,
+            expires_at: Option<T::BlockNumber>,
+            buyer: Option<T::AccountId>,
+            asset_price: Option<(AssetIdOf<T, I>, AssetBalanceOf<T, I>)>,
+        ) -> DispatchResult {
+            // kitty存在
+            ensure!(Kitties::<T, I>::contains_key(kitty_id),Error::<T, I>::InvalidKittyId);
+            // 所有权正确
+            ensure!( Self::kitty_owner(kitty_id) == Some(who.clone()),Error::<T, I>::NotOwner);
+            // 已经在售状态
+            ensure!(!Self::is_on_sale(kitty_id), Error::<T, I>::AlreadyOnSale);
+            ensure!(!KittyBundle::<T, I>::contains_key(kitty_id), Error::<T, I>::AlreadyOnSale);
+            // 待接收转移中
+            Self::ensure_unlocked(kitty_id)?;
+            // 灵魂绑定
+            Self::ensure_transferable(kitty_id)?;
+            // 不能指定自己为买家
+            ensure!(buyer.as_ref() != Some(who), Error::<T, I>::CanNotTransferToSelf);
+            // 过期区块需晚于当前区块，并加入过期清理队列
+            if let Some(expires_at) = expires_at {
+                ensure!(expires_at > <frame_system::Pallet<T>>::block_number(), Error::<T, I>::InvalidExpiry);
+                ListingExpiries::<T, I>::try_append(expires_at, kitty_id)
+                    .map_err(|_| Error::<T, I>::TooManyExpiries)?;
+            }
+            // 标记在售
+            KittyOnSale::<T, I>::insert(kitty_id, Listing { expires_at, buyer: buyer.clone(), asset_price });
+
+            Self::deposit_event(Event::KittyOnSale { who: who.clone(), kitty_id, buyer, asset_price });
+
+            Ok(())
+        }
+        /// 白名单的叶子节点
+        pub fn allowlist_leaf(who: &T::AccountId, quota: u32) -> T::Hash {
+            T::Hashing::hash_of(&(who, quota))
+        }
+        /// 白名单的非叶子节点，子节点排序后再hash，证明中无需标明左右
+        pub fn allowlist_node(a: T::Hash, b: T::Hash) -> T::Hash {
+            if a <= b {
+                T::Hashing::hash_of(&(a, b))
+            } else {
+                T::Hashing::hash_of(&(b, a))
+            }
+        }
+        /// 检查两个父代kitty是否可用于繁衍
+        fn ensure_can_breed(who: &T::AccountId, kitty_id_1: KittyId, kitty_id_2: KittyId) -> DispatchResult {
+            ensure!(kitty_id_1 != kitty_id_2,Error::<T, I>::SameKittyId);
+            ensure!(Kitties::<T, I>::contains_key(kitty_id_1),Error::<T, I>::InvalidKittyId);
+            ensure!(Kitties::<T, I>::contains_key(kitty_id_2),Error::<T, I>::InvalidKittyId);
+            Self::ensure_not_frozen(kitty_id_1)?;
+            Self::ensure_not_frozen(kitty_id_2)?;
+            // 需持有或租借两个kitty
+            Self::ensure_can_use(who, kitty_id_1)?;
+            Self::ensure_can_use(who, kitty_id_2)?;
+            Ok(())
+        }
+        /// 取消两阶段繁衍，refund为true时退还支付的价格
+        fn cancel_breed_commit(kitty_id: KittyId, refund: bool) {
+            if let Some((who, _, _, _)) = Self::remove_breed_commit(kitty_id) {
+                let price = T::KittyPrice::get();
+                let refund = refund
+                    .then(|| T::Currency::transfer(&Self::get_account_id(), &who, price, ExistenceRequirement::AllowDeath).ok())
+                    .flatten()
+                    .map(|_| price);
+                Self::deposit_event(Event::BreedCancelled { who, kitty_id, refund });
+            }
+        }
+        /// 管理员操作使父代不可用时，取消以其为父代的两阶段繁衍并退款
+        fn refund_breed_commits_of(parent: KittyId) {
+            let children: Vec<KittyId> = BreedCommitsByParent::<T, I>::iter_key_prefix(parent).collect();
+            for child in children {
+                Self::cancel_breed_commit(child, true);
+            }
+        }
+        /// 删除两阶段繁衍的提交、随机数及父代索引
+        fn remove_breed_commit(kitty_id: KittyId) -> Option<(T::AccountId, (KittyId, KittyId), [u8; 8], T::BlockNumber)> {
+            let commit = BreedCommits::<T, I>::take(kitty_id)?;
+            let (kitty_id_1, kitty_id_2) = commit.1;
+            BreedCommitsByParent::<T, I>::remove(kitty_id_1, kitty_id);
+            BreedCommitsByParent::<T, I>::remove(kitty_id_2, kitty_id);
+            RevealSeeds::<T, I>::remove(kitty_id);
+            Some(commit)
+        }
+        /// 按selector从两个父代中选取dna
+        fn breed_dna(kitty_id_1: KittyId, kitty_id_2: KittyId, selector: [u8; 16]) -> Result<[u8; 16], DispatchError> {
+            let kitty_1 = Kitties::<T, I>::get(kitty_id_1).ok_or(Error::<T, I>::InvalidKittyId)?;
+            let kitty_2 = Kitties::<T, I>::get(kitty_id_2).ok_or(Error::<T, I>::InvalidKittyId)?;
+
+            let mut data = [0u8; 16];
+            for i in 0..kitty_1.dna.len() {
+                data[i] = (kitty_1.dna[i] & selector[i]) | (kitty_2.dna[i] & !selector[i])
+            }
+            Ok(data)
+        }
+        /// 检查发行量后返回新的kittyId
+        fn next_mint_id() -> Result<KittyId, DispatchError> {
+            let kitty_id = Self::get_next_id()?;
+            ensure!(kitty_id < T::MaxSupply::get(), Error::<T, I>::MaxSupplyReached);
+            Ok(kitty_id)
+        }
+        /// 检查发行量和创建频率后返回新的kittyId
+        fn reserve_mint(who: &T::AccountId) -> Result<KittyId, DispatchError> {
+            let kitty_id = Self::next_mint_id()?;
+            BlockMints::<T, I>::try_mutate(|count| -> DispatchResult {
+                ensure!(*count < T::MaxMintsPerBlock::get(), Error::<T, I>::BlockMintLimitReached);
+                *count += 1;
+                Ok(())
+            })?;
+            AccountMints::<T, I>::try_mutate(who, |count| -> DispatchResult {
+                ensure!(*count < T::MaxMintsPerAccount::get(), Error::<T, I>::AccountMintLimitReached);
+                *count += 1;
+                Ok(())
+            })?;
+            Ok(kitty_id)
+        }
+        /// 返回一个kittyId，并+1后保存为下一个kittyId
+        fn get_next_id() -> Result<KittyId, DispatchError> {
+            NextKittyId::<T, I>::try_mutate(|next_id| -> Result<KittyId, DispatchError> {
+                // 读取当前的 此时完成了copy
+                let current_id = *next_id;
+                // 更新下一个id，可能超出u32的范围，溢出则抛出Error
+                *next_id = next_id.checked_add(1).ok_or::<DispatchError>(Error::<T, I>::InvalidKittyId.into())?;
+                Ok(current_id)
+            })
+        }
+        /// 生成一个随机数
+        fn random_value(sender: &T::AccountId, kitty_id: KittyId) -> [u8; 16] {
+            // 多个参数，确保payload唯一，kitty_id保证同一交易内批量创建的结果不同
+            let payload = (
+                T::Randomness::random_seed(),
+                &sender,
+                <frame_system::Pallet<T>>::extrinsic_index(),
+                kitty_id,
+            );
+            // 用blake2_128确保长度match
+            payload.using_encoded(blake2_128)
+        }
+        /// 存储一致性检查
+        #[cfg(any(feature = "try-runtime", test))]
+        pub fn do_try_state() -> Result<(), &'static str> {
+            let next_kitty_id = Self::next_kitty_id();
+            for kitty_id in Kitties::<T, I>::iter_keys() {
+                ensure!(KittyOwner::<T, I>::contains_key(kitty_id), "Kitty has no owner");
+                ensure!(kitty_id < next_kitty_id, "NextKittyId does not exceed a stored kitty id");
+            }
+            for kitty_id in KittyOnSale::<T, I>::iter_keys() {
+                ensure!(Kitties::<T, I>::contains_key(kitty_id), "Listing refers to a missing kitty");
+            }
+            for (kitty_id, (kitty_id_1, kitty_id_2)) in KittyParents::<T, I>::iter() {
+                ensure!(Kitties::<T, I>::contains_key(kitty_id), "Parents refer to a missing kitty");
+                ensure!(kitty_id_1 < kitty_id && kitty_id_2 < kitty_id, "Parents do not precede the child");
+            }
+            Ok(())
+        }
+        ///
+        fn get_account_id() -> T::AccountId {
+            T::PalletId::get().into_account_truncating()
+        }
+        /// 分页查询未过期的公开在售，不包含指定买家的私下出售
+        /// 从start之后开始，最多返回limit条，limit不超过MAX_LISTINGS_PAGE
+        pub fn public_listings(start: Option<KittyId>, limit: u32) -> Vec<(KittyId, ListingOf<T, I>)> {
+            let now = <frame_system::Pallet<T>>::block_number();
+            let listings = match start {
+                Some(kitty_id) => KittyOnSale::<T, I>::iter_from(KittyOnSale::<T, I>::hashed_key_for(kitty_id)),
+                None => KittyOnSale::<T, I>::iter(),
+            };
+            listings
+                .filter(|(_, listing)| !listing.is_private())
+                .filter(|(_, listing)| listing.expires_at.map_or(true, |expires_at| now < expires_at))
+                .take(limit.min(MAX_LISTINGS_PAGE) as usize)
+                .collect()
+        }
+        /// 票数最多的选项，平票时没有胜出选项
+        pub fn poll_winner(tally: &[u32]) -> Option<u32> {
+            let max = *tally.iter().max()?;
+            let mut leaders = tally.iter().enumerate().filter(|(_, &count)| count == max);
+            match (leaders.next(), leaders.next()) {
+                (Some((index, _)), None) if max > 0 => Some(index as u32),
+                _ => None,
+            }
+        }
+        /// 各稀有度对应的dna[0]范围(含两端)，与rarity_weight的分档一致
+        pub fn rarity_range(rarity: u8) -> Option<(u8, u8)> {
+            match rarity {
+                0 => Some((0, 179)),
+                1 => Some((180, 229)),
+                2 => Some((230, 249)),
+                3 => Some((250, 255)),
+                _ => None,
+            }
+        }
+        /// 由随机数按权重选出稀有度，相同的随机数总是得到相同的结果
+        pub fn pick_rarity(table: &[(u8, u32)], seed: [u8; 16]) -> Option<u8> {
+            let total = table.iter().fold(0u32, |total, (_, weight)| total.saturating_add(*weight));
+            if total == 0 {
+                return None;
+            }
+            let mut roll = u32::from_le_bytes([seed[0], seed[1], seed[2], seed[3]]) % total;
+            for &(rarity, weight) in table {
+                if roll < weight {
+                    return Some(rarity);
+                }
+                roll -= weight;
+            }
+            None
+        }
+        /// 生成指定稀有度的dna，dna[0]落在该稀有度的范围内
+        pub fn rarity_dna(rarity: u8, seed: [u8; 16]) -> Option<[u8; 16]> {
+            let (low, high) = Self::rarity_range(rarity)?;
+            let mut dna = blake2_128(&seed);
+            dna[0] = low + dna[0] % (high - low + 1);
+            Some(dna)
+        }
+        /// 盲盒各稀有度的概率
+        pub fn mystery_box_odds(box_id: MysteryBoxId) -> Vec<(u8, Perbill)> {
+            let table = match Self::mystery_box(box_id) {
+                Some((_, table)) => table,
+                None => return Vec::new(),
+            };
+            let total = table.iter().fold(0u32, |total, (_, weight)| total.saturating_add(*weight));
+            table.iter().map(|&(rarity, weight)| (rarity, Perbill::from_rational(weight, total))).collect()
+        }
+        /// 由dna[0]决定的稀有度权重
+        pub fn rarity_weight(dna: &[u8; 16]) -> u32 {
+            match dna[0] {
+                250..=255 => 10,
+                230..=249 => 5,
+                180..=229 => 2,
+                _ => 1,
+            }
+        }
+        /// 质押kitty自上次结算以来累计的奖励
+        pub fn pending_reward(kitty_id: KittyId) -> BalanceOf<T, I> {
+            let (since, kitty) = match (Self::staked_kitty(kitty_id), Self::kitties(kitty_id)) {
+                (Some((_, since)), Some(kitty)) => (since, kitty),
+                _ => return Zero::zero(),
+            };
+            let blocks: u32 = <frame_system::Pallet<T>>::block_number().saturating_sub(since).unique_saturated_into();
+            T::StakingRewardPerBlock::get()
+                .saturating_mul(blocks.into())
+                .saturating_mul(Self::rarity_weight(&kitty.dna).into())
+        }
+        /// 经验对应的等级
+        pub fn level_of(experience: u32) -> u8 {
+            let level = experience.checked_div(T::XpPerLevel::get()).unwrap_or_default();
+            level.min(u8::MAX as u32) as u8
+        }
+        /// 由随机数选出一个dna字节并替换，保证变异后的dna与原来不同
+        pub fn mutate_dna(mut dna: [u8; 16], roll: [u8; 16]) -> [u8; 16] {
+            let index = roll[0] as usize % dna.len();
+            dna[index] = dna[index].wrapping_add(roll[1].max(1));
+            dna
+        }
+        /// 挑战方是否获胜，平局由守方获胜
+        pub fn resolve_battle(challenger: &KittyStats, defender: &KittyStats, roll: [u8; 16]) -> bool {
+            let attack = challenger.attack as u32 * 2 + challenger.speed as u32 + roll[0] as u32;
+            let defense = defender.defense as u32 * 2 + defender.speed as u32 + roll[1] as u32;
+            attack > defense
+        }
+        /// 进行已接受的战斗，胜者取回押注并获得奖励；任一kitty已不存在时退还押注
+        fn settle_battle(kitty_id: KittyId, challenger: T::AccountId, opponent_id: KittyId, opponent: T::AccountId) {
+            let (kitty, opponent_kitty) = match (Self::kitties(kitty_id), Self::kitties(opponent_id)) {
+                (Some(kitty), Some(opponent_kitty)) => (kitty, opponent_kitty),
+                _ => return Self::cancel_battle(kitty_id),
+            };
+            BattleChallenges::<T, I>::remove(kitty_id);
+
+            let (seed, _) = T::Randomness::random(&(b"kitties/battle", kitty_id, opponent_id).encode());
+            let roll = seed.using_encoded(blake2_128);
+            let won = Self::resolve_battle(&KittyStats::from_dna(&kitty.dna), &KittyStats::from_dna(&opponent_kitty.dna), roll);
+            let (winner, loser, winner_account) = if won {
+                (kitty_id, opponent_id, &challenger)
+            } else {
+                (opponent_id, kitty_id, &opponent)
+            };
+            BattleRecords::<T, I>::mutate(winner, |(wins, _)| *wins = wins.saturating_add(1));
+            BattleRecords::<T, I>::mutate(loser, |(_, losses)| *losses = losses.saturating_add(1));
+
+            // 胜者取回押注并从pallet账户获得奖励，败者的押注留在pallet账户
+            let reward = T::BattleReward::get();
+            let payout = T::BattleStake::get().saturating_add(reward);
+            let reward = T::Currency::transfer(&Self::get_account_id(), winner_account, payout, ExistenceRequirement::AllowDeath)
+                .ok()
+                .map(|_| reward);
+
+            Self::deposit_event(Event::KittyBattled { who: challenger, kitty_id, opponent_id, winner, reward });
+        }
+        /// 取消战斗挑战并退还双方已支付的押注
+        fn cancel_battle(kitty_id: KittyId) {
+            if let Some((challenger, _, accepted)) = BattleChallenges::<T, I>::take(kitty_id) {
+                let stake = T::BattleStake::get();
+                let pallet_account = Self::get_account_id();
+                if let Some((opponent, _)) = accepted {
+                    let _ = T::Currency::transfer(&pallet_account, &opponent, stake, ExistenceRequirement::AllowDeath);
+                }
+                let _ = T::Currency::transfer(&pallet_account, &challenger, stake, ExistenceRequirement::AllowDeath);
+                Self::deposit_event(Event::ChallengeCancelled { who: challenger, kitty_id });
+            }
+        }
+        /// 被冻结或处于战斗冷却中的kitty不能战斗
+        fn ensure_can_battle(kitty_id: KittyId) -> DispatchResult {
+            Self::ensure_not_frozen(kitty_id)?;
+            if let Some(until) = Self::battle_cooldown(kitty_id) {
+                ensure!(<frame_system::Pallet<T>>::block_number() >= until, Error::<T, I>::KittyInCooldown);
+            }
+            Ok(())
+        }
+        /// 链下订单的签名内容，以创世区块哈希区分不同的链，以PalletId区分不同的合集
+        pub fn order_payload(order: &Order<BalanceOf<T, I>, T::BlockNumber>) -> Vec<u8> {
+            let genesis_hash = <frame_system::Pallet<T>>::block_hash(T::BlockNumber::zero());
+            (genesis_hash, T::PalletId::get(), order).encode()
+        }
+        /// 释放kitty登记的名称，返回是否存在登记
+        fn unregister_name(kitty_id: KittyId) -> bool {
+            let name = match Self::kitties(kitty_id) {
+                Some(kitty) => kitty.name,
+                None => return false,
+            };
+            if Self::name_to_kitty(name) != Some(kitty_id) {
+                return false;
+            }
+            NameToKitty::<T, I>::remove(name);
+            Self::deposit_event(Event::NameReleased { kitty_id, name });
+            true
+        }
+        /// 是否处于未过期的在售状态
+        fn is_on_sale(kitty_id: KittyId) -> bool {
+            match Self::kitty_on_sale(kitty_id) {
+                Some(Listing { expires_at: Some(expires_at), .. }) => <frame_system::Pallet<T>>::block_number() < expires_at,
+                Some(_) => true,
+                None => false,
+            }
+        }
+        /// 变更owner，并清理上一个owner遗留的转移、交换要约
+        fn set_owner(kitty_id: KittyId, owner: &T::AccountId) {
+            Self::insert_owner(kitty_id, owner);
+            PendingTransfers::<T, I>::remove(kitty_id);
+            SwapOrders::<T, I>::remove(kitty_id);
+            Self::invalidate_bundle(kitty_id);
+        }
+        /// 记录owner及其取得kitty的区块
+        fn insert_owner(kitty_id: KittyId, owner: &T::AccountId) {
+            KittyOwner::<T, I>::insert(kitty_id, owner);
+            KittyAcquiredAt::<T, I>::insert(kitty_id, <frame_system::Pallet<T>>::block_number());
+        }
+        /// 任一kitty转移后，其所在的打包出售失效
+        fn invalidate_bundle(kitty_id: KittyId) {
+            if let Some(bundle_id) = KittyBundle::<T, I>::get(kitty_id) {
+                Self::remove_bundle(bundle_id);
+                Self::deposit_event(Event::BundleInvalidated { bundle_id });
+            }
+        }
+        /// 删除打包出售及其kitty索引
+        fn remove_bundle(bundle_id: BundleId) -> Option<(T::AccountId, BoundedVec<KittyId, T::MaxBundleSize>, BalanceOf<T, I>)> {
+            let bundle = Bundles::<T, I>::take(bundle_id)?;
+            for kitty_id in bundle.1.iter() {
+                KittyBundle::<T, I>::remove(kitty_id);
+            }
+            Some(bundle)
+        }
+        /// 灵魂绑定的kitty不能转移和出售
+        fn ensure_transferable(kitty_id: KittyId) -> DispatchResult {
+            let kitty = Self::kitties(kitty_id).ok_or(Error::<T, I>::InvalidKittyId)?;
+            ensure!(!kitty.soulbound, Error::<T, I>::Soulbound);
+            Ok(())
+        }
+        /// 被冻结的kitty不能进行任何操作
+        fn ensure_not_frozen(kitty_id: KittyId) -> DispatchResult {
+            ensure!(!FrozenKitties::<T, I>::contains_key(kitty_id), Error::<T, I>::KittyFrozen);
+            Ok(())
+        }
+        /// 清理kitty的在售、待接收转移、交换、出租、质押和抵押借款状态
+        fn clear_kitty_state(kitty_id: KittyId) {
+            Self::invalidate_bundle(kitty_id);
+            KittyOnSale::<T, I>::remove(kitty_id);
+            PendingTransfers::<T, I>::remove(kitty_id);
+            SwapOrders::<T, I>::remove(kitty_id);
+            RentalOffers::<T, I>::remove(kitty_id);
+            KittyRentals::<T, I>::remove(kitty_id);
+            StakedKitties::<T, I>::remove(kitty_id);
+            LoanRequests::<T, I>::remove(kitty_id);
+            Loans::<T, I>::remove(kitty_id);
+            MutationCommits::<T, I>::remove(kitty_id);
+        }
+        /// 存在未过期的待接收转移、交换要约，或处于出租、质押、抵押、待变异中时，kitty被锁定
+        fn ensure_unlocked(kitty_id: KittyId) -> DispatchResult {
+            Self::ensure_not_frozen(kitty_id)?;
+            let now = <frame_system::Pallet<T>>::block_number();
+            if let Some((_, expires_at)) = PendingTransfers::<T, I>::get(kitty_id) {
+                ensure!(now >= expires_at, Error::<T, I>::KittyLocked);
+            }
+            if let Some((_, _, _, expires_at)) = SwapOrders::<T, I>::get(kitty_id) {
+                ensure!(now >= expires_at, Error::<T, I>::KittyLocked);
+            }
+            ensure!(!RentalOffers::<T, I>::contains_key(kitty_id), Error::<T, I>::KittyLocked);
+            ensure!(!KittyRentals::<T, I>::contains_key(kitty_id), Error::<T, I>::KittyLocked);
+            ensure!(!StakedKitties::<T, I>::contains_key(kitty_id), Error::<T, I>::KittyLocked);
+            ensure!(!LoanRequests::<T, I>::contains_key(kitty_id), Error::<T, I>::KittyLocked);
+            ensure!(!Loans::<T, I>::contains_key(kitty_id), Error::<T, I>::KittyLocked);
+            ensure!(!MutationCommits::<T, I>::contains_key(kitty_id), Error::<T, I>::KittyLocked);
+            // 作为待揭示繁衍的父代时锁定，防止揭示前转走父代取消繁衍
+            ensure!(BreedCommitsByParent::<T, I>::iter_key_prefix(kitty_id).next().is_none(), Error::<T, I>::KittyLocked);
+            Ok(())
+        }
+        /// 出租中的kitty由租借人使用，否则由owner使用
+        fn ensure_can_use(who: &T::AccountId, kitty_id: KittyId) -> DispatchResult {
+            let user = match Self::kitty_rental(kitty_id) {
+                Some((borrower, _)) => Some(borrower),
+                None => Self::kitty_owner(kitty_id),
+            };
+            ensure!(user.as_ref() == Some(who), Error::<T, I>::NotOwner);
+            Ok(())
         }
     }
 }
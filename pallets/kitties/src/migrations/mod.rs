@@ -5,14 +5,19 @@ use frame_support::{
 };
 
 use mod_extra::Migrate;
-pub use v2 as current_version;
+pub use v7 as current_version;
 
 use crate::{Config, Pallet};
 
 mod mod_extra;
 mod v0;
 mod v1;
-pub mod v2;
+mod v2;
+mod v3;
+mod v4;
+mod v5;
+mod v6;
+pub mod v7;
 
 // type FnMigrate<T: Config> = fn() -> Weight;
 //
@@ -21,21 +26,26 @@ pub mod v2;
 //     (v2::STORAGE_VERSION, v2::m_test::<T>),
 // ];
 
-pub fn migrate<T: Config>() -> Weight {
-    let version: [(StorageVersion, fn() -> Weight); 3] = [
-        (v0::STORAGE_VERSION, v0::Upgrade::migrate::<T>),
-        (v1::STORAGE_VERSION, v1::Upgrade::migrate::<T>),
-        (v2::STORAGE_VERSION, v2::Upgrade::migrate::<T>),
+pub fn migrate<T: Config<I>, I: 'static>() -> Weight {
+    let version: [(StorageVersion, fn() -> Weight); 8] = [
+        (v0::STORAGE_VERSION, v0::Upgrade::migrate::<T, I>),
+        (v1::STORAGE_VERSION, v1::Upgrade::migrate::<T, I>),
+        (v2::STORAGE_VERSION, v2::Upgrade::migrate::<T, I>),
+        (v3::STORAGE_VERSION, v3::Upgrade::migrate::<T, I>),
+        (v4::STORAGE_VERSION, v4::Upgrade::migrate::<T, I>),
+        (v5::STORAGE_VERSION, v5::Upgrade::migrate::<T, I>),
+        (v6::STORAGE_VERSION, v6::Upgrade::migrate::<T, I>),
+        (v7::STORAGE_VERSION, v7::Upgrade::migrate::<T, I>),
     ];
     // 链式升级，直至最终版本
-    let on_chain_ver: StorageVersion = Pallet::<T>::on_chain_storage_version();
+    let on_chain_ver: StorageVersion = Pallet::<T, I>::on_chain_storage_version();
     // 需要一个版本链
     // let idx = version.index(on_chain_ver);
     for (ver, upgrade) in version.iter() {
         if on_chain_ver.lt(ver) {
             // upgrade::migrate::<T>();
             upgrade();
-            ver.put::<Pallet::<T>>();
+            ver.put::<Pallet::<T, I>>();
         }
     }
     // 需要一个map，保存从onChainVer升级至下一个版本的方法
@@ -2,5 +2,5 @@ use frame_support::weights::Weight;
 use crate::Config;
 
 pub trait Migrate {
-    fn migrate<T: Config>() -> Weight;
+    fn migrate<T: Config<I>, I: 'static>() -> Weight;
 }
\ No newline at end of file
@@ -18,13 +18,13 @@ pub type KittyId = u32;
 pub struct Kitty (pub [u8; 16]);
 
 #[storage_alias]
-pub(super) type Kitties<T: Config> = StorageMap<Pallet<T>, Blake2_128Concat, KittyId, Kitty>;
+pub(super) type Kitties<T: Config<I>, I: 'static> = StorageMap<Pallet<T, I>, Blake2_128Concat, KittyId, Kitty>;
 
 
 pub(crate) struct Upgrade;
 
 impl Migrate for Upgrade {
-    fn migrate<T: Config>() -> Weight {
+    fn migrate<T: Config<I>, I: 'static>() -> Weight {
         Weight::zero()
     }
 }
\ No newline at end of file
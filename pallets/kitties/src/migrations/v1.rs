@@ -24,7 +24,7 @@ pub struct Kitty {
 }
 
 #[storage_alias]
-pub(super) type Kitties<T: Config> = StorageMap<Pallet<T>, Blake2_128Concat, KittyId, Kitty>;
+pub(super) type Kitties<T: Config<I>, I: 'static> = StorageMap<Pallet<T, I>, Blake2_128Concat, KittyId, Kitty>;
 
 /// 上个版本的定义
 type OldKitty = v0::Kitty;
@@ -33,9 +33,9 @@ pub(crate) struct Upgrade;
 
 /// 从v0~v1
 impl Migrate for Upgrade {
-    fn migrate<T: Config>() -> Weight {
-        let on_chain_version = Pallet::<T>::on_chain_storage_version();
-        let current_version = Pallet::<T>::current_storage_version();
+    fn migrate<T: Config<I>, I: 'static>() -> Weight {
+        let on_chain_version = Pallet::<T, I>::on_chain_storage_version();
+        let current_version = Pallet::<T, I>::current_storage_version();
 
         if on_chain_version != 0 {
             return Weight::zero();
@@ -45,15 +45,15 @@ impl Migrate for Upgrade {
             return Weight::zero();
         }
 
-        let module = Kitties::<T>::module_prefix();
-        let item = Kitties::<T>::storage_prefix();
+        let module = Kitties::<T, I>::module_prefix();
+        let item = Kitties::<T, I>::storage_prefix();
 
         for (index, kitty) in storage_key_iter::<KittyId, OldKitty, Blake2_128Concat>(module, item).drain() {
             let new_kitty = Kitty {
                 dna: kitty.0,
                 name: *b"NULL",
             };
-            Kitties::<T>::insert(index, new_kitty);
+            Kitties::<T, I>::insert(index, new_kitty);
         }
 
         Weight::zero()
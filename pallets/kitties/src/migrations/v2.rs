@@ -24,7 +24,7 @@ pub struct Kitty {
 }
 
 #[storage_alias]
-pub(super) type Kitties<T: Config> = StorageMap<Pallet<T>, Blake2_128Concat, KittyId, Kitty>;
+pub(super) type Kitties<T: Config<I>, I: 'static> = StorageMap<Pallet<T, I>, Blake2_128Concat, KittyId, Kitty>;
 
 
 /// 上个版本的定义
@@ -33,9 +33,9 @@ type OldKitty = v1::Kitty;
 pub(crate) struct Upgrade;
 
 impl Migrate for Upgrade {
-    fn migrate<T: Config>() -> Weight {
-        let on_chain_version = Pallet::<T>::on_chain_storage_version();
-        let current_version = Pallet::<T>::current_storage_version();
+    fn migrate<T: Config<I>, I: 'static>() -> Weight {
+        let on_chain_version = Pallet::<T, I>::on_chain_storage_version();
+        let current_version = Pallet::<T, I>::current_storage_version();
 
         if on_chain_version != 1 {
             return Weight::zero();
@@ -44,15 +44,15 @@ impl Migrate for Upgrade {
         if current_version < 2 {
             return Weight::zero();
         }
-        let module = Kitties::<T>::module_prefix();
-        let item = Kitties::<T>::storage_prefix();
+        let module = Kitties::<T, I>::module_prefix();
+        let item = Kitties::<T, I>::storage_prefix();
 
         for (index, kitty) in storage_key_iter::<KittyId, OldKitty, Blake2_128Concat>(module, item).drain() {
             let mut name = [0u8; 8];
             name[0..4].copy_from_slice(&kitty.name[..4]);
             name[4..].copy_from_slice(b"0000");
             let new_kitty = Kitty { dna: kitty.dna, name };
-            Kitties::<T>::insert(index, &new_kitty);
+            Kitties::<T, I>::insert(index, &new_kitty);
         }
 
         Weight::zero()
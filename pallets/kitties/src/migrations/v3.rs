@@ -0,0 +1,55 @@
+use frame_support::{
+    pallet_prelude::*,
+    traits::GetStorageVersion,
+    weights::Weight,
+    Blake2_128Concat,
+    storage_alias,
+};
+use crate::{Config, Pallet};
+use super::{v2, mod_extra::Migrate};
+
+/// 当前版本的定义
+pub const STORAGE_VERSION: StorageVersion = StorageVersion::new(3);
+
+/// ID
+pub type KittyId = v2::KittyId;
+
+/// 数据存储的类型和长度
+pub type Kitty = v2::Kitty;
+
+/// 在售信息
+#[derive(Encode, Decode, Clone, Copy, RuntimeDebug, PartialEq, Eq, Default, TypeInfo, MaxEncodedLen)]
+pub struct Listing<BlockNumber> {
+    /// 过期区块，None表示永不过期
+    pub expires_at: Option<BlockNumber>,
+}
+
+#[storage_alias]
+pub(super) type KittyOnSale<T: Config<I>, I: 'static> = StorageMap<Pallet<T, I>, Blake2_128Concat, KittyId, Listing<<T as frame_system::Config>::BlockNumber>>;
+
+#[storage_alias]
+pub(super) type ListingPruneCursor<T: Config<I>, I: 'static> = StorageValue<Pallet<T, I>, <T as frame_system::Config>::BlockNumber, ValueQuery>;
+
+pub(crate) struct Upgrade;
+
+/// 从v2~v3，已有的在售状态转为永不过期的在售信息
+impl Migrate for Upgrade {
+    fn migrate<T: Config<I>, I: 'static>() -> Weight {
+        let on_chain_version = Pallet::<T, I>::on_chain_storage_version();
+        let current_version = Pallet::<T, I>::current_storage_version();
+
+        if on_chain_version != 2 {
+            return Weight::zero();
+        }
+
+        if current_version < 3 {
+            return Weight::zero();
+        }
+
+        KittyOnSale::<T, I>::translate::<(), _>(|_, _| Some(Listing { expires_at: None }));
+        // 过期清理从当前区块开始
+        ListingPruneCursor::<T, I>::put(<frame_system::Pallet<T>>::block_number());
+
+        Weight::zero()
+    }
+}
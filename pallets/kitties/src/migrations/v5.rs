@@ -0,0 +1,69 @@
+use frame_support::{
+    pallet_prelude::*,
+    traits::GetStorageVersion,
+    weights::Weight,
+    Blake2_128Concat,
+    storage_alias,
+};
+use crate::{Config, Pallet};
+use super::{v4, mod_extra::Migrate};
+
+/// 当前版本的定义
+pub const STORAGE_VERSION: StorageVersion = StorageVersion::new(5);
+
+/// ID
+pub type KittyId = v4::KittyId;
+
+/// 数据存储的类型和长度
+pub type Kitty = v4::Kitty;
+
+/// 在售信息
+#[derive(Encode, Decode, Clone, Copy, RuntimeDebug, PartialEq, Eq, Default, TypeInfo, MaxEncodedLen)]
+pub struct Listing<AccountId, BlockNumber> {
+    /// 过期区块，None表示永不过期
+    pub expires_at: Option<BlockNumber>,
+    /// 指定的买家，None表示公开出售
+    pub buyer: Option<AccountId>,
+}
+
+impl<AccountId, BlockNumber> Listing<AccountId, BlockNumber> {
+    /// 是否为指定买家的私下出售
+    pub fn is_private(&self) -> bool {
+        self.buyer.is_some()
+    }
+}
+
+#[storage_alias]
+pub(super) type KittyOnSale<T: Config<I>, I: 'static> = StorageMap<
+    Pallet<T, I>,
+    Blake2_128Concat,
+    KittyId,
+    Listing<<T as frame_system::Config>::AccountId, <T as frame_system::Config>::BlockNumber>,
+>;
+
+/// 上个版本的定义
+type OldListing<BlockNumber> = v4::Listing<BlockNumber>;
+
+pub(crate) struct Upgrade;
+
+/// 从v4~v5，已有的在售均为公开出售
+impl Migrate for Upgrade {
+    fn migrate<T: Config<I>, I: 'static>() -> Weight {
+        let on_chain_version = Pallet::<T, I>::on_chain_storage_version();
+        let current_version = Pallet::<T, I>::current_storage_version();
+
+        if on_chain_version != 4 {
+            return Weight::zero();
+        }
+
+        if current_version < 5 {
+            return Weight::zero();
+        }
+
+        KittyOnSale::<T, I>::translate::<OldListing<T::BlockNumber>, _>(|_, listing| {
+            Some(Listing { expires_at: listing.expires_at, buyer: None })
+        });
+
+        Weight::zero()
+    }
+}
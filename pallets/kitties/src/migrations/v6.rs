@@ -0,0 +1,76 @@
+use frame_support::{
+    pallet_prelude::*,
+    storage::StoragePrefixedMap,
+    traits::GetStorageVersion,
+    weights::Weight,
+    migration::storage_key_iter,
+    Blake2_128Concat,
+    storage_alias,
+};
+use crate::{Config, Pallet};
+use super::{v5, mod_extra::Migrate};
+
+/// 当前版本的定义
+pub const STORAGE_VERSION: StorageVersion = StorageVersion::new(6);
+
+/// ID
+pub type KittyId = v5::KittyId;
+
+/// 在售信息
+pub type Listing<AccountId, BlockNumber> = v5::Listing<AccountId, BlockNumber>;
+
+/// 数据存储的类型和长度
+/// 之后的版本升级需保留soulbound标记和成长信息
+#[derive(Encode, Decode, Clone, Copy, RuntimeDebug, PartialEq, Eq, Default, TypeInfo, MaxEncodedLen)]
+pub struct Kitty {
+    pub dna: [u8; 16],
+    pub name: [u8; 8],
+    /// 灵魂绑定，不能转移和出售
+    pub soulbound: bool,
+    /// 喂养获得的经验
+    pub experience: u32,
+    /// 由经验决定的等级
+    pub level: u8,
+    /// 是否已经使用过dna变异
+    pub mutated: bool,
+}
+
+#[storage_alias]
+pub(super) type Kitties<T: Config<I>, I: 'static> = StorageMap<Pallet<T, I>, Blake2_128Concat, KittyId, Kitty>;
+
+/// 上个版本的定义
+type OldKitty = v5::Kitty;
+
+pub(crate) struct Upgrade;
+
+/// 从v5~v6，已有的kitty从0级开始
+impl Migrate for Upgrade {
+    fn migrate<T: Config<I>, I: 'static>() -> Weight {
+        let on_chain_version = Pallet::<T, I>::on_chain_storage_version();
+        let current_version = Pallet::<T, I>::current_storage_version();
+
+        if on_chain_version != 5 {
+            return Weight::zero();
+        }
+
+        if current_version < 6 {
+            return Weight::zero();
+        }
+        let module = Kitties::<T, I>::module_prefix();
+        let item = Kitties::<T, I>::storage_prefix();
+
+        for (index, kitty) in storage_key_iter::<KittyId, OldKitty, Blake2_128Concat>(module, item).drain() {
+            let new_kitty = Kitty {
+                dna: kitty.dna,
+                name: kitty.name,
+                soulbound: kitty.soulbound,
+                experience: 0,
+                level: 0,
+                mutated: false,
+            };
+            Kitties::<T, I>::insert(index, &new_kitty);
+        }
+
+        Weight::zero()
+    }
+}
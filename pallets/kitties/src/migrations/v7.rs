@@ -0,0 +1,76 @@
+use frame_support::{
+    pallet_prelude::*,
+    traits::GetStorageVersion,
+    weights::Weight,
+    Blake2_128Concat,
+    storage_alias,
+};
+use crate::{AssetBalanceOf, AssetIdOf, Config, Pallet};
+use super::{v6, mod_extra::Migrate};
+
+/// 当前版本的定义
+pub const STORAGE_VERSION: StorageVersion = StorageVersion::new(7);
+
+/// ID
+pub type KittyId = v6::KittyId;
+
+/// 数据存储的类型和长度
+pub type Kitty = v6::Kitty;
+
+/// 在售信息
+#[derive(Encode, Decode, Clone, Copy, RuntimeDebug, PartialEq, Eq, Default, TypeInfo, MaxEncodedLen)]
+pub struct Listing<AccountId, BlockNumber, AssetId, AssetBalance> {
+    /// 过期区块，None表示永不过期
+    pub expires_at: Option<BlockNumber>,
+    /// 指定的买家，None表示公开出售
+    pub buyer: Option<AccountId>,
+    /// 以资产支付的价格，None表示以原生币支付KittyPrice
+    pub asset_price: Option<(AssetId, AssetBalance)>,
+}
+
+impl<AccountId, BlockNumber, AssetId, AssetBalance> Listing<AccountId, BlockNumber, AssetId, AssetBalance> {
+    /// 是否为指定买家的私下出售
+    pub fn is_private(&self) -> bool {
+        self.buyer.is_some()
+    }
+}
+
+#[storage_alias]
+pub(super) type KittyOnSale<T: Config<I>, I: 'static> = StorageMap<
+    Pallet<T, I>,
+    Blake2_128Concat,
+    KittyId,
+    Listing<
+        <T as frame_system::Config>::AccountId,
+        <T as frame_system::Config>::BlockNumber,
+        AssetIdOf<T, I>,
+        AssetBalanceOf<T, I>,
+    >,
+>;
+
+/// 上个版本的定义
+type OldListing<AccountId, BlockNumber> = v6::Listing<AccountId, BlockNumber>;
+
+pub(crate) struct Upgrade;
+
+/// 从v6~v7，已有的在售均以原生币支付
+impl Migrate for Upgrade {
+    fn migrate<T: Config<I>, I: 'static>() -> Weight {
+        let on_chain_version = Pallet::<T, I>::on_chain_storage_version();
+        let current_version = Pallet::<T, I>::current_storage_version();
+
+        if on_chain_version != 6 {
+            return Weight::zero();
+        }
+
+        if current_version < 7 {
+            return Weight::zero();
+        }
+
+        KittyOnSale::<T, I>::translate::<OldListing<T::AccountId, T::BlockNumber>, _>(|_, listing| {
+            Some(Listing { expires_at: listing.expires_at, buyer: listing.buyer, asset_price: None })
+        });
+
+        Weight::zero()
+    }
+}
@@ -1,11 +1,14 @@
 use crate as pallet_kitties;
-use frame_support::traits::{ConstU128, ConstU16, ConstU32, ConstU64};
+use frame_support::traits::{AsEnsureOriginWithArg, ConstU128, ConstU16, ConstU32, ConstU64, ConstU8};
 use frame_support::parameter_types;
+use frame_support::weights::constants::RocksDbWeight;
 use frame_support::PalletId;
+pub use frame_support::instances::Instance1;
 use sp_core::H256;
 use sp_runtime::{
-    testing::Header,
+    testing::{Header, TestSignature, UintAuthorityId},
     traits::{BlakeTwo256, IdentityLookup},
+    Perbill,
 };
 use pallet_insecure_randomness_collective_flip;
 use pallet_balances;
@@ -26,7 +29,9 @@ frame_support::construct_runtime!(
         Balances: pallet_balances,
 		System: frame_system,
 		KittiesModule: pallet_kitties,
+		KittiesCollection2: pallet_kitties::<Instance1>,
 		Randomness: pallet_insecure_randomness_collective_flip,
+		Assets: pallet_assets,
 	}
 );
 
@@ -34,7 +39,7 @@ impl frame_system::Config for Test {
     type BaseCallFilter = frame_support::traits::Everything;
     type BlockWeights = ();
     type BlockLength = ();
-    type DbWeight = ();
+    type DbWeight = RocksDbWeight;
     type RuntimeOrigin = RuntimeOrigin;
     type RuntimeCall = RuntimeCall;
     type Index = u64;
@@ -78,18 +83,112 @@ pub const EXISTENTIAL_DEPOSIT: u128 = 500;
 parameter_types! {
 	pub KittyPalletId: PalletId = PalletId(*b"py/kitty");
 	pub KittyPrice: Balance = EXISTENTIAL_DEPOSIT * 10;
+	pub AllowlistPrice: Balance = EXISTENTIAL_DEPOSIT * 5;
+	pub const MaxSupply: u32 = 100;
+	pub BattleStake: Balance = EXISTENTIAL_DEPOSIT * 2;
+	pub BattleReward: Balance = EXISTENTIAL_DEPOSIT;
+	pub FeedPrice: Balance = 100;
+	pub MutationPrice: Balance = 200;
+	pub LoanToValue: Perbill = Perbill::from_percent(50);
+	pub LoanInterest: Perbill = Perbill::from_percent(10);
+	pub Collection2PalletId: PalletId = PalletId(*b"py/ktty2");
+	pub Collection2Price: Balance = EXISTENTIAL_DEPOSIT * 20;
 }
 
 impl pallet_kitties::Config for Test {
     type RuntimeEvent = RuntimeEvent;
     type Randomness = Randomness;
     type Currency = Balances;
+    type Assets = Assets;
     type KittyPrice = KittyPrice;
     type PalletId = KittyPalletId;
+    type MaxSupply = MaxSupply;
+    type MaxMintsPerBlock = ConstU32<8>;
+    type MaxMintsPerAccount = ConstU32<5>;
+    type ForceOrigin = frame_system::EnsureRoot<u64>;
+    type AllowlistPrice = AllowlistPrice;
+    type MaxBundleSize = ConstU32<3>;
+    type MaxBatchSize = ConstU32<5>;
+    type MaxExpiriesPerBlock = ConstU32<4>;
+    type PendingTransferExpiry = ConstU64<10>;
+    type BreedRevealDelay = ConstU64<3>;
+    type SwapExpiry = ConstU64<10>;
+    type OffchainSignature = TestSignature;
+    type OffchainPublic = UintAuthorityId;
+    type BattleCooldown = ConstU64<5>;
+    type BattleStake = BattleStake;
+    type BattleReward = BattleReward;
+    type FeedPrice = FeedPrice;
+    type XpPerFeed = ConstU32<10>;
+    type XpPerLevel = ConstU32<100>;
+    type MutationLevel = ConstU8<3>;
+    type MutationPrice = MutationPrice;
+    type StakingRewardPerBlock = ConstU128<10>;
+    type LoanToValue = LoanToValue;
+    type LoanInterest = LoanInterest;
+    type MaxPollOptions = ConstU32<4>;
+}
+
+/// 第二个Kitty合集，独立的存储、价格和资金账户
+impl pallet_kitties::Config<Instance1> for Test {
+    type RuntimeEvent = RuntimeEvent;
+    type Randomness = Randomness;
+    type Currency = Balances;
+    type Assets = Assets;
+    type KittyPrice = Collection2Price;
+    type PalletId = Collection2PalletId;
+    type MaxSupply = MaxSupply;
+    type MaxMintsPerBlock = ConstU32<8>;
+    type MaxMintsPerAccount = ConstU32<5>;
+    type ForceOrigin = frame_system::EnsureRoot<u64>;
+    type AllowlistPrice = AllowlistPrice;
+    type MaxBundleSize = ConstU32<3>;
+    type MaxBatchSize = ConstU32<5>;
+    type MaxExpiriesPerBlock = ConstU32<4>;
+    type PendingTransferExpiry = ConstU64<10>;
+    type BreedRevealDelay = ConstU64<3>;
+    type SwapExpiry = ConstU64<10>;
+    type OffchainSignature = TestSignature;
+    type OffchainPublic = UintAuthorityId;
+    type BattleCooldown = ConstU64<5>;
+    type BattleStake = BattleStake;
+    type BattleReward = BattleReward;
+    type FeedPrice = FeedPrice;
+    type XpPerFeed = ConstU32<10>;
+    type XpPerLevel = ConstU32<100>;
+    type MutationLevel = ConstU8<3>;
+    type MutationPrice = MutationPrice;
+    type StakingRewardPerBlock = ConstU128<10>;
+    type LoanToValue = LoanToValue;
+    type LoanInterest = LoanInterest;
+    type MaxPollOptions = ConstU32<4>;
 }
 
 impl pallet_insecure_randomness_collective_flip::Config for Test {}
 
+impl pallet_assets::Config for Test {
+    type RuntimeEvent = RuntimeEvent;
+    type Balance = Balance;
+    type AssetId = u32;
+    type AssetIdParameter = codec::Compact<u32>;
+    type Currency = Balances;
+    type CreateOrigin = AsEnsureOriginWithArg<frame_system::EnsureSigned<u64>>;
+    type ForceOrigin = frame_system::EnsureRoot<u64>;
+    type AssetDeposit = ConstU128<1>;
+    type AssetAccountDeposit = ConstU128<1>;
+    type MetadataDepositBase = ConstU128<1>;
+    type MetadataDepositPerByte = ConstU128<1>;
+    type ApprovalDeposit = ConstU128<1>;
+    type StringLimit = ConstU32<50>;
+    type Freezer = ();
+    type Extra = ();
+    type CallbackHandle = ();
+    type WeightInfo = ();
+    type RemoveItemsLimit = ConstU32<1000>;
+    #[cfg(feature = "runtime-benchmarks")]
+    type BenchmarkHelper = ();
+}
+
 // Build genesis storage according to the mock runtime.
 pub fn new_test_ext() -> sp_io::TestExternalities {
     let mut ext: sp_io::TestExternalities = frame_system::GenesisConfig::default().build_storage::<Test>().unwrap().into();
@@ -97,6 +196,19 @@ pub fn new_test_ext() -> sp_io::TestExternalities {
     ext
 }
 
+/// 执行测试，并在结束时检查两个合集的存储一致性
+pub fn build_and_execute(test: impl FnOnce()) {
+    new_test_ext().execute_with(|| {
+        test();
+        KittiesModule::do_try_state().expect("KittiesModule storage is consistent");
+        KittiesCollection2::do_try_state().expect("KittiesCollection2 storage is consistent");
+    });
+}
+
 pub fn get_account_id() -> <Test as frame_system::Config>::AccountId {
     KittyPalletId::get().into_account_truncating()
-}
\ No newline at end of file
+}
+
+pub fn get_collection2_account_id() -> <Test as frame_system::Config>::AccountId {
+    Collection2PalletId::get().into_account_truncating()
+}
@@ -1,14 +1,40 @@
-use crate::{mock::*, Error, Event};
-use frame_support::{assert_noop, assert_ok};
+use crate::{mock::*, Error, MAX_LISTINGS_PAGE};
+use pallet_test_utils::{check, Rng};
+use frame_support::{assert_noop, assert_ok, traits::{GenesisBuild, Hooks}, weights::Weight};
+use codec::Encode;
+use sp_core::H256;
+use sp_runtime::{testing::TestSignature, DispatchError};
 
 
 const ACCOUNT_BALANCE: u128 = 100000;
+const KITTY_NAME: [u8; 8] = *b"abcdefgh";
+
+/// 默认实例的事件
+type Event = crate::Event<Test>;
+
+/// 以给定的父区块hash推进到指定区块，随机数来源于这些区块
+fn run_to_block_with_hash(n: u64, parent_hash: H256) {
+    while System::block_number() < n {
+        let next = System::block_number() + 1;
+        System::initialize(&next, &parent_hash, &Default::default());
+        Randomness::on_initialize(next);
+        KittiesModule::on_initialize(next);
+    }
+}
+
+/// 推进到指定区块，并执行on_initialize
+fn run_to_block(n: u64) {
+    while System::block_number() < n {
+        System::set_block_number(System::block_number() + 1);
+        KittiesModule::on_initialize(System::block_number());
+    }
+}
 
 
 ///  创建Kitty
 #[test]
 fn create_kitty() {
-    new_test_ext().execute_with(|| {
+    build_and_execute(|| {
         let kitty_id = 0;
         let account_id = 1;
         // 预设余额
@@ -17,7 +43,7 @@ fn create_kitty() {
         // 验空
         assert_eq!(KittiesModule::next_kitty_id(), kitty_id);
         // 验证kitty创建正常
-        assert_ok!(KittiesModule::create_kitty(RuntimeOrigin::signed(account_id)));
+        assert_ok!(KittiesModule::create_kitty(RuntimeOrigin::signed(account_id), KITTY_NAME));
         // 创建事件验证
         let kitty = KittiesModule::kitties(kitty_id).expect("Kitty Created");
         System::assert_last_event(Event::KittyCreated {
@@ -41,7 +67,7 @@ fn create_kitty() {
         crate::NextKittyId::<Test>::set(crate::KittyId::MAX);
         // 验证重复
         assert_noop!(
-            KittiesModule::create_kitty(RuntimeOrigin::signed(account_id)),
+            KittiesModule::create_kitty(RuntimeOrigin::signed(account_id), KITTY_NAME),
             Error::<Test>::InvalidKittyId
         );
     })
@@ -50,7 +76,7 @@ fn create_kitty() {
 /// 繁衍 Kitty
 #[test]
 fn breed_kitty() {
-    new_test_ext().execute_with(|| {
+    build_and_execute(|| {
         let kitty_id = 0;
         let account_id = 1;
         // 预设余额
@@ -58,7 +84,7 @@ fn breed_kitty() {
 
         // 验证俩父代相同
         assert_noop!(
-            KittiesModule::breed(RuntimeOrigin::signed(account_id),kitty_id,kitty_id),
+            KittiesModule::breed(RuntimeOrigin::signed(account_id),kitty_id,kitty_id,KITTY_NAME),
             Error::<Test>::SameKittyId
         );
         // 验证余额没有扣减
@@ -66,15 +92,15 @@ fn breed_kitty() {
 
         // 验证kitty不存在
         assert_noop!(
-            KittiesModule::breed(RuntimeOrigin::signed(account_id),kitty_id,kitty_id+1),
+            KittiesModule::breed(RuntimeOrigin::signed(account_id),kitty_id,kitty_id+1,KITTY_NAME),
             Error::<Test>::InvalidKittyId
         );
         // 验证余额没有扣减
         assert_eq!(Balances::free_balance(account_id), ACCOUNT_BALANCE);
 
         // 验证创建两个kitty成功
-        assert_ok!(KittiesModule::create_kitty(RuntimeOrigin::signed(account_id)));
-        assert_ok!(KittiesModule::create_kitty(RuntimeOrigin::signed(account_id)));
+        assert_ok!(KittiesModule::create_kitty(RuntimeOrigin::signed(account_id), KITTY_NAME));
+        assert_ok!(KittiesModule::create_kitty(RuntimeOrigin::signed(account_id), KITTY_NAME));
         // 验证余额扣减正确
         assert_eq!(Balances::free_balance(account_id), ACCOUNT_BALANCE - 2 * KittyPrice::get());
         assert_eq!(Balances::free_balance(&get_account_id()), 2 * KittyPrice::get());
@@ -84,7 +110,8 @@ fn breed_kitty() {
         // 验证kitty繁衍成功
         assert_ok!(KittiesModule::breed(RuntimeOrigin::signed(account_id),
             kitty_id,
-            kitty_id+1
+            kitty_id+1,
+            KITTY_NAME
         ));
         // 验证余额扣减正确
         assert_eq!(Balances::free_balance(account_id), ACCOUNT_BALANCE - 3 * KittyPrice::get());
@@ -118,7 +145,7 @@ fn breed_kitty() {
 /// 转移 Kitty
 #[test]
 fn transfer_kitty() {
-    new_test_ext().execute_with(|| {
+    build_and_execute(|| {
         let kitty_id = 0;
         let account_id = 1;
         let recipient = 2;
@@ -127,7 +154,7 @@ fn transfer_kitty() {
         assert_ok!(Balances::set_balance(RuntimeOrigin::root(), recipient, ACCOUNT_BALANCE, 0));
 
         // 验证kitty创建正常
-        assert_ok!(KittiesModule::create_kitty(RuntimeOrigin::signed(account_id)));
+        assert_ok!(KittiesModule::create_kitty(RuntimeOrigin::signed(account_id), KITTY_NAME));
         // 验证创建的kitty所有者正确
         assert_eq!(KittiesModule::kitty_owner(kitty_id), Some(account_id));
         // 验证非持有者不能转移
@@ -185,7 +212,7 @@ fn transfer_kitty() {
 /// 标记 Kitty 可售
 #[test]
 fn sale_kitty() {
-    new_test_ext().execute_with(|| {
+    build_and_execute(|| {
         let kitty_id = 0;
         let account_id = 1;
         // 预设余额
@@ -193,27 +220,27 @@ fn sale_kitty() {
 
         // 验空
         assert_noop!(
-			KittiesModule::sale(RuntimeOrigin::signed(account_id), kitty_id),
+			KittiesModule::sale(RuntimeOrigin::signed(account_id), kitty_id, None, None, None),
 			Error::<Test>::InvalidKittyId
 		);
         // 验证kitty创建正常
-        assert_ok!(KittiesModule::create_kitty(RuntimeOrigin::signed(account_id)));
+        assert_ok!(KittiesModule::create_kitty(RuntimeOrigin::signed(account_id), KITTY_NAME));
         // 验证余额扣减正确
         assert_eq!(Balances::free_balance(account_id), ACCOUNT_BALANCE - KittyPrice::get());
         assert_eq!(Balances::free_balance(&get_account_id()), KittyPrice::get());
         // 所有权不正确
         assert_noop!(
-			KittiesModule::sale(RuntimeOrigin::signed(account_id + 1), kitty_id),
+			KittiesModule::sale(RuntimeOrigin::signed(account_id + 1), kitty_id, None, None, None),
 			Error::<Test>::NotOwner
 		);
         // 标记成功
-        assert_ok!(KittiesModule::sale(RuntimeOrigin::signed(account_id), kitty_id));
+        assert_ok!(KittiesModule::sale(RuntimeOrigin::signed(account_id), kitty_id, None, None, None));
         assert!(KittiesModule::kitty_on_sale(kitty_id).is_some());
-        System::assert_last_event(Event::KittyOnSale { who: account_id, kitty_id }.into());
+        System::assert_last_event(Event::KittyOnSale { who: account_id, kitty_id, buyer: None, asset_price: None }.into());
 
         // 已经在售
         assert_noop!(
-			KittiesModule::sale(RuntimeOrigin::signed(account_id), kitty_id),
+			KittiesModule::sale(RuntimeOrigin::signed(account_id), kitty_id, None, None, None),
 			Error::<Test>::AlreadyOnSale
 		);
     })
@@ -222,7 +249,7 @@ fn sale_kitty() {
 /// 购买 Kitty
 #[test]
 fn buy_kitty() {
-    new_test_ext().execute_with(|| {
+    build_and_execute(|| {
         let kitty_id = 0;
         let account_id = 1;
         let account_id_2 = 2;
@@ -236,7 +263,7 @@ fn buy_kitty() {
 			Error::<Test>::InvalidKittyId
 		);
         // 验证kitty创建正常
-        assert_ok!(KittiesModule::create_kitty(RuntimeOrigin::signed(account_id)));
+        assert_ok!(KittiesModule::create_kitty(RuntimeOrigin::signed(account_id), KITTY_NAME));
         // 验证余额扣减正确
         assert_eq!(Balances::free_balance(account_id), ACCOUNT_BALANCE - KittyPrice::get());
         assert_eq!(Balances::free_balance(&get_account_id()), KittyPrice::get());
@@ -251,9 +278,9 @@ fn buy_kitty() {
 			Error::<Test>::NotOnSale
 		);
         // 标记在售成功
-        assert_ok!(KittiesModule::sale(RuntimeOrigin::signed(account_id), kitty_id));
+        assert_ok!(KittiesModule::sale(RuntimeOrigin::signed(account_id), kitty_id, None, None, None));
         assert!(KittiesModule::kitty_on_sale(kitty_id).is_some());
-        System::assert_last_event(Event::KittyOnSale { who: account_id, kitty_id }.into());
+        System::assert_last_event(Event::KittyOnSale { who: account_id, kitty_id, buyer: None, asset_price: None }.into());
         // 购买成功
         assert_ok!(KittiesModule::buy(RuntimeOrigin::signed(account_id_2), kitty_id));
         // 验证相关结果
@@ -264,4 +291,2214 @@ fn buy_kitty() {
 
         System::assert_last_event(Event::KittyBought { who: account_id_2, kitty_id }.into());
     })
-}
\ No newline at end of file
+}
+/// 发起转移并接收
+#[test]
+fn offer_and_accept_transfer() {
+    build_and_execute(|| {
+        let kitty_id = 0;
+        let account_id = 1;
+        let recipient = 2;
+        // 预设余额
+        assert_ok!(Balances::set_balance(RuntimeOrigin::root(), account_id, ACCOUNT_BALANCE, 0));
+        assert_ok!(KittiesModule::create_kitty(RuntimeOrigin::signed(account_id), KITTY_NAME));
+
+        // 非持有者不能发起转移
+        assert_noop!(
+            KittiesModule::offer_transfer(RuntimeOrigin::signed(recipient), kitty_id, recipient),
+            Error::<Test>::NotOwner
+        );
+        // 不能转移给自己
+        assert_noop!(
+            KittiesModule::offer_transfer(RuntimeOrigin::signed(account_id), kitty_id, account_id),
+            Error::<Test>::CanNotTransferToSelf
+        );
+        // 发起成功，owner不变
+        assert_ok!(KittiesModule::offer_transfer(RuntimeOrigin::signed(account_id), kitty_id, recipient));
+        assert_eq!(KittiesModule::kitty_owner(kitty_id), Some(account_id));
+        assert_eq!(KittiesModule::pending_transfer(kitty_id), Some((recipient, 11)));
+        System::assert_last_event(Event::TransferOffered {
+            who: account_id,
+            recipient,
+            kitty_id,
+            expires_at: 11,
+        }.into());
+
+        // 待接收期间kitty被锁定
+        assert_noop!(
+            KittiesModule::transfer(RuntimeOrigin::signed(account_id), 3, kitty_id),
+            Error::<Test>::KittyLocked
+        );
+        assert_noop!(
+            KittiesModule::sale(RuntimeOrigin::signed(account_id), kitty_id, None, None, None),
+            Error::<Test>::KittyLocked
+        );
+        assert_noop!(
+            KittiesModule::offer_transfer(RuntimeOrigin::signed(account_id), kitty_id, 3),
+            Error::<Test>::KittyLocked
+        );
+        // 非接收人不能接收
+        assert_noop!(
+            KittiesModule::accept_transfer(RuntimeOrigin::signed(3), kitty_id),
+            Error::<Test>::NotRecipient
+        );
+
+        // 接收成功
+        assert_ok!(KittiesModule::accept_transfer(RuntimeOrigin::signed(recipient), kitty_id));
+        assert_eq!(KittiesModule::kitty_owner(kitty_id), Some(recipient));
+        assert_eq!(KittiesModule::pending_transfer(kitty_id), None);
+        System::assert_last_event(Event::TransferAccepted {
+            who: recipient,
+            from: account_id,
+            kitty_id,
+        }.into());
+        // 不能重复接收
+        assert_noop!(
+            KittiesModule::accept_transfer(RuntimeOrigin::signed(recipient), kitty_id),
+            Error::<Test>::NoPendingTransfer
+        );
+    })
+}
+
+/// 拒绝、撤销转移，以及转移过期
+#[test]
+fn reject_cancel_and_expire_transfer() {
+    build_and_execute(|| {
+        let kitty_id = 0;
+        let account_id = 1;
+        let recipient = 2;
+        // 预设余额
+        assert_ok!(Balances::set_balance(RuntimeOrigin::root(), account_id, ACCOUNT_BALANCE, 0));
+        assert_ok!(KittiesModule::create_kitty(RuntimeOrigin::signed(account_id), KITTY_NAME));
+
+        // 接收人拒绝
+        assert_ok!(KittiesModule::offer_transfer(RuntimeOrigin::signed(account_id), kitty_id, recipient));
+        assert_noop!(
+            KittiesModule::reject_transfer(RuntimeOrigin::signed(account_id), kitty_id),
+            Error::<Test>::NotRecipient
+        );
+        assert_ok!(KittiesModule::reject_transfer(RuntimeOrigin::signed(recipient), kitty_id));
+        assert_eq!(KittiesModule::pending_transfer(kitty_id), None);
+        System::assert_last_event(Event::TransferRejected { who: recipient, kitty_id }.into());
+
+        // owner撤销
+        assert_ok!(KittiesModule::offer_transfer(RuntimeOrigin::signed(account_id), kitty_id, recipient));
+        assert_noop!(
+            KittiesModule::cancel_transfer(RuntimeOrigin::signed(recipient), kitty_id),
+            Error::<Test>::NotOwner
+        );
+        assert_ok!(KittiesModule::cancel_transfer(RuntimeOrigin::signed(account_id), kitty_id));
+        assert_eq!(KittiesModule::pending_transfer(kitty_id), None);
+        System::assert_last_event(Event::TransferCancelled { who: account_id, kitty_id }.into());
+        assert_noop!(
+            KittiesModule::cancel_transfer(RuntimeOrigin::signed(account_id), kitty_id),
+            Error::<Test>::NoPendingTransfer
+        );
+
+        // 过期后不能接收，且kitty解除锁定
+        assert_ok!(KittiesModule::offer_transfer(RuntimeOrigin::signed(account_id), kitty_id, recipient));
+        System::set_block_number(11);
+        assert_noop!(
+            KittiesModule::accept_transfer(RuntimeOrigin::signed(recipient), kitty_id),
+            Error::<Test>::TransferExpired
+        );
+        assert_ok!(KittiesModule::transfer(RuntimeOrigin::signed(account_id), 3, kitty_id));
+        assert_eq!(KittiesModule::kitty_owner(kitty_id), Some(3));
+        assert_eq!(KittiesModule::pending_transfer(kitty_id), None);
+    })
+}
+
+/// 批量创建、转移、标记可售
+#[test]
+fn batch_operations() {
+    build_and_execute(|| {
+        let account_id = 1;
+        let recipient = 2;
+        // 预设余额
+        assert_ok!(Balances::set_balance(RuntimeOrigin::root(), account_id, ACCOUNT_BALANCE, 0));
+
+        // 超出最大数量
+        assert_noop!(
+            KittiesModule::batch_create(RuntimeOrigin::signed(account_id), vec![KITTY_NAME; 6]),
+            Error::<Test>::BatchTooLarge
+        );
+        // 批量创建成功
+        assert_ok!(KittiesModule::batch_create(RuntimeOrigin::signed(account_id), vec![KITTY_NAME; 3]));
+        assert_eq!(KittiesModule::next_kitty_id(), 3);
+        assert_eq!(Balances::free_balance(account_id), ACCOUNT_BALANCE - 3 * KittyPrice::get());
+        // 同一交易内创建的kitty dna不同
+        assert_ne!(KittiesModule::kitties(0).unwrap().dna, KittiesModule::kitties(1).unwrap().dna);
+
+        // 任一失败则全部回滚
+        assert_noop!(
+            KittiesModule::batch_transfer(RuntimeOrigin::signed(account_id), vec![(recipient, 0), (recipient, 3)]),
+            Error::<Test>::InvalidKittyId
+        );
+        assert_eq!(KittiesModule::kitty_owner(0), Some(account_id));
+        // 批量转移成功
+        assert_ok!(KittiesModule::batch_transfer(RuntimeOrigin::signed(account_id), vec![(recipient, 0), (recipient, 1)]));
+        assert_eq!(KittiesModule::kitty_owner(0), Some(recipient));
+        assert_eq!(KittiesModule::kitty_owner(1), Some(recipient));
+
+        // 非持有者的kitty导致全部回滚
+        assert_noop!(
+            KittiesModule::batch_sale(RuntimeOrigin::signed(account_id), vec![2, 0], None),
+            Error::<Test>::NotOwner
+        );
+        assert!(KittiesModule::kitty_on_sale(2).is_none());
+        // 批量标记成功
+        assert_ok!(KittiesModule::batch_sale(RuntimeOrigin::signed(recipient), vec![0, 1], None));
+        assert!(KittiesModule::kitty_on_sale(0).is_some());
+        assert!(KittiesModule::kitty_on_sale(1).is_some());
+
+        // 余额不足时批量创建全部回滚
+        let poor = 3;
+        assert_ok!(Balances::set_balance(RuntimeOrigin::root(), poor, KittyPrice::get() * 2, 0));
+        assert_noop!(
+            KittiesModule::batch_create(RuntimeOrigin::signed(poor), vec![KITTY_NAME; 2]),
+            pallet_balances::Error::<Test>::KeepAlive
+        );
+        assert_eq!(KittiesModule::next_kitty_id(), 3);
+    })
+}
+
+/// 出租 Kitty
+#[test]
+fn lend_kitty() {
+    build_and_execute(|| {
+        let kitty_id = 0;
+        let owner = 1;
+        let borrower = 2;
+        let fee = 1_000;
+        // 预设余额
+        assert_ok!(Balances::set_balance(RuntimeOrigin::root(), owner, ACCOUNT_BALANCE, 0));
+        assert_ok!(Balances::set_balance(RuntimeOrigin::root(), borrower, ACCOUNT_BALANCE, 0));
+        assert_ok!(KittiesModule::create_kitty(RuntimeOrigin::signed(owner), KITTY_NAME));
+        assert_ok!(KittiesModule::create_kitty(RuntimeOrigin::signed(borrower), KITTY_NAME));
+
+        // 非持有者不能出租
+        assert_noop!(
+            KittiesModule::lend(RuntimeOrigin::signed(borrower), kitty_id, borrower, 5, fee),
+            Error::<Test>::NotOwner
+        );
+        // 租期不能为0
+        assert_noop!(
+            KittiesModule::lend(RuntimeOrigin::signed(owner), kitty_id, borrower, 0, fee),
+            Error::<Test>::InvalidDuration
+        );
+        // 出租要约
+        assert_ok!(KittiesModule::lend(RuntimeOrigin::signed(owner), kitty_id, borrower, 5, fee));
+        System::assert_last_event(Event::RentalOffered { who: owner, borrower, kitty_id, duration: 5, fee }.into());
+        // 非租借人不能确认
+        assert_noop!(
+            KittiesModule::borrow(RuntimeOrigin::signed(3), kitty_id),
+            Error::<Test>::NotBorrower
+        );
+        // 租借生效，支付租金，owner不变
+        assert_ok!(KittiesModule::borrow(RuntimeOrigin::signed(borrower), kitty_id));
+        System::assert_last_event(Event::KittyLent { owner, borrower, kitty_id, expires_at: 6 }.into());
+        assert_eq!(KittiesModule::kitty_owner(kitty_id), Some(owner));
+        assert_eq!(KittiesModule::kitty_rental(kitty_id), Some((borrower, 6)));
+        assert_eq!(Balances::free_balance(owner), ACCOUNT_BALANCE - KittyPrice::get() + fee);
+        assert_eq!(Balances::free_balance(borrower), ACCOUNT_BALANCE - KittyPrice::get() - fee);
+
+        // 出租期间owner不能转移、出售、繁衍
+        assert_noop!(
+            KittiesModule::transfer(RuntimeOrigin::signed(owner), 3, kitty_id),
+            Error::<Test>::KittyLocked
+        );
+        assert_noop!(
+            KittiesModule::sale(RuntimeOrigin::signed(owner), kitty_id, None, None, None),
+            Error::<Test>::KittyLocked
+        );
+        assert_ok!(KittiesModule::create_kitty(RuntimeOrigin::signed(owner), KITTY_NAME));
+        assert_noop!(
+            KittiesModule::breed(RuntimeOrigin::signed(owner), kitty_id, 2, KITTY_NAME),
+            Error::<Test>::NotOwner
+        );
+        // 租借人不能转移、出售
+        assert_noop!(
+            KittiesModule::transfer(RuntimeOrigin::signed(borrower), 3, kitty_id),
+            Error::<Test>::NotOwner
+        );
+        assert_noop!(
+            KittiesModule::sale(RuntimeOrigin::signed(borrower), kitty_id, None, None, None),
+            Error::<Test>::NotOwner
+        );
+        // 租借人可以繁衍
+        assert_ok!(KittiesModule::breed(RuntimeOrigin::signed(borrower), kitty_id, 1, KITTY_NAME));
+        assert_eq!(KittiesModule::kitty_owner(3), Some(borrower));
+
+        // 租期结束自动归还
+        run_to_block(6);
+        System::assert_last_event(Event::KittyReturned { borrower, kitty_id }.into());
+        assert_eq!(KittiesModule::kitty_rental(kitty_id), None);
+        assert_noop!(
+            KittiesModule::breed(RuntimeOrigin::signed(borrower), kitty_id, 1, KITTY_NAME),
+            Error::<Test>::NotOwner
+        );
+        assert_ok!(KittiesModule::transfer(RuntimeOrigin::signed(owner), 3, kitty_id));
+    })
+}
+
+/// 撤销出租要约
+#[test]
+fn cancel_lend_kitty() {
+    build_and_execute(|| {
+        let kitty_id = 0;
+        let owner = 1;
+        let borrower = 2;
+        // 预设余额
+        assert_ok!(Balances::set_balance(RuntimeOrigin::root(), owner, ACCOUNT_BALANCE, 0));
+        assert_ok!(KittiesModule::create_kitty(RuntimeOrigin::signed(owner), KITTY_NAME));
+
+        assert_noop!(
+            KittiesModule::cancel_lend(RuntimeOrigin::signed(owner), kitty_id),
+            Error::<Test>::NoRentalOffer
+        );
+        assert_ok!(KittiesModule::lend(RuntimeOrigin::signed(owner), kitty_id, borrower, 5, 0));
+        // 要约期间kitty被锁定
+        assert_noop!(
+            KittiesModule::transfer(RuntimeOrigin::signed(owner), borrower, kitty_id),
+            Error::<Test>::KittyLocked
+        );
+        assert_noop!(
+            KittiesModule::cancel_lend(RuntimeOrigin::signed(borrower), kitty_id),
+            Error::<Test>::NotOwner
+        );
+        assert_ok!(KittiesModule::cancel_lend(RuntimeOrigin::signed(owner), kitty_id));
+        System::assert_last_event(Event::RentalCancelled { who: owner, kitty_id }.into());
+        assert_noop!(
+            KittiesModule::borrow(RuntimeOrigin::signed(borrower), kitty_id),
+            Error::<Test>::NoRentalOffer
+        );
+        assert_ok!(KittiesModule::transfer(RuntimeOrigin::signed(owner), borrower, kitty_id));
+    })
+}
+
+/// 交换 Kitty
+#[test]
+fn swap_kitty() {
+    build_and_execute(|| {
+        let proposer = 1;
+        let other = 2;
+        let top_up = 1_000;
+        // 预设余额
+        assert_ok!(Balances::set_balance(RuntimeOrigin::root(), proposer, ACCOUNT_BALANCE, 0));
+        assert_ok!(Balances::set_balance(RuntimeOrigin::root(), other, ACCOUNT_BALANCE, 0));
+        assert_ok!(KittiesModule::create_kitty(RuntimeOrigin::signed(proposer), KITTY_NAME));
+        assert_ok!(KittiesModule::create_kitty(RuntimeOrigin::signed(other), KITTY_NAME));
+
+        // 非持有者不能发起
+        assert_noop!(
+            KittiesModule::propose_swap(RuntimeOrigin::signed(other), 0, 1, None),
+            Error::<Test>::NotOwner
+        );
+        // 不能与自己的kitty交换
+        assert_ok!(KittiesModule::create_kitty(RuntimeOrigin::signed(proposer), KITTY_NAME));
+        assert_noop!(
+            KittiesModule::propose_swap(RuntimeOrigin::signed(proposer), 0, 2, None),
+            Error::<Test>::AlreadyOwned
+        );
+        // 发起交换
+        assert_ok!(KittiesModule::propose_swap(RuntimeOrigin::signed(proposer), 0, 1, Some(top_up)));
+        System::assert_last_event(Event::SwapProposed {
+            who: proposer,
+            kitty_id: 0,
+            their_kitty_id: 1,
+            top_up: Some(top_up),
+            expires_at: 11,
+        }.into());
+        // 要约期间kitty被锁定
+        assert_noop!(
+            KittiesModule::transfer(RuntimeOrigin::signed(proposer), 3, 0),
+            Error::<Test>::KittyLocked
+        );
+        // 只有对方kitty的owner能确认
+        assert_noop!(
+            KittiesModule::accept_swap(RuntimeOrigin::signed(3), 0),
+            Error::<Test>::NotOwner
+        );
+        // 确认交换，owner与补差价同时完成
+        assert_ok!(KittiesModule::accept_swap(RuntimeOrigin::signed(other), 0));
+        System::assert_last_event(Event::KittySwapped {
+            who: other,
+            kitty_id: 0,
+            proposer,
+            their_kitty_id: 1,
+        }.into());
+        assert_eq!(KittiesModule::kitty_owner(0), Some(other));
+        assert_eq!(KittiesModule::kitty_owner(1), Some(proposer));
+        assert_eq!(KittiesModule::swap_order(0), None);
+        assert_eq!(Balances::free_balance(proposer), ACCOUNT_BALANCE - 2 * KittyPrice::get() - top_up);
+        assert_eq!(Balances::free_balance(other), ACCOUNT_BALANCE - KittyPrice::get() + top_up);
+        assert_noop!(
+            KittiesModule::accept_swap(RuntimeOrigin::signed(other), 0),
+            Error::<Test>::NoSwapOrder
+        );
+    })
+}
+
+/// 撤销交换要约，以及要约过期
+#[test]
+fn cancel_and_expire_swap() {
+    build_and_execute(|| {
+        let proposer = 1;
+        let other = 2;
+        // 预设余额
+        assert_ok!(Balances::set_balance(RuntimeOrigin::root(), proposer, ACCOUNT_BALANCE, 0));
+        assert_ok!(Balances::set_balance(RuntimeOrigin::root(), other, ACCOUNT_BALANCE, 0));
+        assert_ok!(KittiesModule::create_kitty(RuntimeOrigin::signed(proposer), KITTY_NAME));
+        assert_ok!(KittiesModule::create_kitty(RuntimeOrigin::signed(other), KITTY_NAME));
+
+        // 撤销
+        assert_ok!(KittiesModule::propose_swap(RuntimeOrigin::signed(proposer), 0, 1, None));
+        assert_noop!(
+            KittiesModule::cancel_swap(RuntimeOrigin::signed(other), 0),
+            Error::<Test>::NotOwner
+        );
+        assert_ok!(KittiesModule::cancel_swap(RuntimeOrigin::signed(proposer), 0));
+        System::assert_last_event(Event::SwapCancelled { who: proposer, kitty_id: 0 }.into());
+        assert_noop!(
+            KittiesModule::accept_swap(RuntimeOrigin::signed(other), 0),
+            Error::<Test>::NoSwapOrder
+        );
+
+        // 过期后不能确认，kitty解除锁定
+        assert_ok!(KittiesModule::propose_swap(RuntimeOrigin::signed(proposer), 0, 1, None));
+        System::set_block_number(11);
+        assert_noop!(
+            KittiesModule::accept_swap(RuntimeOrigin::signed(other), 0),
+            Error::<Test>::SwapExpired
+        );
+        assert_ok!(KittiesModule::transfer(RuntimeOrigin::signed(proposer), 3, 0));
+        assert_eq!(KittiesModule::swap_order(0), None);
+    })
+}
+
+/// 在售过期，并在on_idle中清理
+#[test]
+fn listing_expires() {
+    build_and_execute(|| {
+        let kitty_id = 0;
+        let seller = 1;
+        let buyer = 2;
+        // 预设余额
+        assert_ok!(Balances::set_balance(RuntimeOrigin::root(), seller, ACCOUNT_BALANCE, 0));
+        assert_ok!(Balances::set_balance(RuntimeOrigin::root(), buyer, ACCOUNT_BALANCE, 0));
+        assert_ok!(KittiesModule::create_kitty(RuntimeOrigin::signed(seller), KITTY_NAME));
+        assert_ok!(KittiesModule::create_kitty(RuntimeOrigin::signed(seller), KITTY_NAME));
+
+        // 过期区块需晚于当前区块
+        assert_noop!(
+            KittiesModule::sale(RuntimeOrigin::signed(seller), kitty_id, Some(1), None, None),
+            Error::<Test>::InvalidExpiry
+        );
+        assert_ok!(KittiesModule::sale(RuntimeOrigin::signed(seller), kitty_id, Some(3), None, None));
+        assert_ok!(KittiesModule::sale(RuntimeOrigin::signed(seller), 1, None, None, None));
+
+        // 过期后即使尚未清理也不能购买
+        System::set_block_number(3);
+        assert_noop!(
+            KittiesModule::buy(RuntimeOrigin::signed(buyer), kitty_id),
+            Error::<Test>::ListingExpired
+        );
+
+        // weight不足时不清理
+        assert_eq!(KittiesModule::on_idle(3, Weight::zero()), Weight::zero());
+        assert!(KittiesModule::kitty_on_sale(kitty_id).is_some());
+
+        // on_idle清理过期的在售，永不过期的保留
+        KittiesModule::on_idle(3, Weight::MAX);
+        System::assert_last_event(Event::ListingExpired { kitty_id }.into());
+        assert!(KittiesModule::kitty_on_sale(kitty_id).is_none());
+        assert!(KittiesModule::kitty_on_sale(1).is_some());
+        assert_eq!(crate::ListingPruneCursor::<Test>::get(), 4);
+
+        // 清理后可以重新上架
+        assert_ok!(KittiesModule::sale(RuntimeOrigin::signed(seller), kitty_id, Some(5), None, None));
+        assert_ok!(KittiesModule::buy(RuntimeOrigin::signed(buyer), kitty_id));
+        assert_eq!(KittiesModule::kitty_owner(kitty_id), Some(buyer));
+    })
+}
+
+/// 发行量与创建频率限制
+#[test]
+fn mint_limits() {
+    build_and_execute(|| {
+        // 预设余额
+        for account_id in 1..=3 {
+            assert_ok!(Balances::set_balance(RuntimeOrigin::root(), account_id, ACCOUNT_BALANCE, 0));
+        }
+
+        // 单个账户在一个区块内的上限
+        assert_ok!(KittiesModule::batch_create(RuntimeOrigin::signed(1), vec![KITTY_NAME; 5]));
+        assert_eq!(KittiesModule::account_mints(1), 5);
+        assert_noop!(
+            KittiesModule::create_kitty(RuntimeOrigin::signed(1), KITTY_NAME),
+            Error::<Test>::AccountMintLimitReached
+        );
+        // 繁衍同样计入
+        assert_noop!(
+            KittiesModule::breed(RuntimeOrigin::signed(1), 0, 1, KITTY_NAME),
+            Error::<Test>::AccountMintLimitReached
+        );
+
+        // 整个区块的上限
+        assert_ok!(KittiesModule::batch_create(RuntimeOrigin::signed(2), vec![KITTY_NAME; 3]));
+        assert_eq!(KittiesModule::block_mints(), 8);
+        assert_noop!(
+            KittiesModule::create_kitty(RuntimeOrigin::signed(3), KITTY_NAME),
+            Error::<Test>::BlockMintLimitReached
+        );
+
+        // 新区块重置计数
+        run_to_block(2);
+        assert_eq!(KittiesModule::block_mints(), 0);
+        assert_eq!(KittiesModule::account_mints(1), 0);
+        assert_ok!(KittiesModule::create_kitty(RuntimeOrigin::signed(1), KITTY_NAME));
+
+        // 最大发行量
+        crate::NextKittyId::<Test>::set(MaxSupply::get());
+        assert_noop!(
+            KittiesModule::create_kitty(RuntimeOrigin::signed(3), KITTY_NAME),
+            Error::<Test>::MaxSupplyReached
+        );
+    })
+}
+
+/// 提交两阶段繁衍，并在之后以给定的区块hash生成dna
+fn commit_and_reveal(parent_hash: H256) -> [u8; 16] {
+    let mut dna = [0u8; 16];
+    build_and_execute(|| {
+        let account_id = 1;
+        let kitty_id = 2;
+        // 预设余额
+        assert_ok!(Balances::set_balance(RuntimeOrigin::root(), account_id, ACCOUNT_BALANCE, 0));
+        assert_ok!(KittiesModule::create_kitty(RuntimeOrigin::signed(account_id), KITTY_NAME));
+        assert_ok!(KittiesModule::create_kitty(RuntimeOrigin::signed(account_id), KITTY_NAME));
+
+        // 非持有者不能提交
+        assert_noop!(
+            KittiesModule::commit_breed(RuntimeOrigin::signed(2), 0, 1, KITTY_NAME),
+            Error::<Test>::NotOwner
+        );
+        // 提交时支付并占用kittyId，但kitty尚未生成
+        assert_ok!(KittiesModule::commit_breed(RuntimeOrigin::signed(account_id), 0, 1, KITTY_NAME));
+        System::assert_last_event(Event::BreedCommitted { who: account_id, kitty_id, reveal_at: 4 }.into());
+        assert_eq!(Balances::free_balance(account_id), ACCOUNT_BALANCE - 3 * KittyPrice::get());
+        assert_eq!(KittiesModule::next_kitty_id(), kitty_id + 1);
+        assert!(KittiesModule::kitties(kitty_id).is_none());
+
+        // 未到达reveal区块
+        run_to_block_with_hash(3, parent_hash);
+        assert_noop!(
+            KittiesModule::reveal_breed(RuntimeOrigin::signed(3), kitty_id),
+            Error::<Test>::BreedNotReady
+        );
+
+        // 任何人都可以生成
+        run_to_block_with_hash(4, parent_hash);
+        assert_ok!(KittiesModule::reveal_breed(RuntimeOrigin::signed(3), kitty_id));
+        let kitty = KittiesModule::kitties(kitty_id).expect("Breed Kitty Revealed");
+        System::assert_last_event(Event::KittyBred { who: account_id, kitty_id, kitty }.into());
+        assert_eq!(KittiesModule::kitty_owner(kitty_id), Some(account_id));
+        assert_eq!(KittiesModule::kitty_parents(kitty_id), Some((0, 1)));
+        assert_noop!(
+            KittiesModule::reveal_breed(RuntimeOrigin::signed(3), kitty_id),
+            Error::<Test>::NoBreedCommit
+        );
+        dna = kitty.dna;
+    });
+    dna
+}
+
+/// 两阶段繁衍：提交时的状态完全相同，dna仅取决于提交之后的区块
+#[test]
+fn commit_reveal_breed() {
+    assert_ne!(commit_and_reveal(H256::repeat_byte(1)), commit_and_reveal(H256::repeat_byte(2)));
+    assert_eq!(commit_and_reveal(H256::repeat_byte(1)), commit_and_reveal(H256::repeat_byte(1)));
+}
+
+/// 两阶段繁衍：随机数在reveal区块确定，推迟reveal不能改变dna
+#[test]
+fn reveal_breed_seed_fixed_at_reveal_block() {
+    let dna = commit_and_reveal(H256::repeat_byte(1));
+    build_and_execute(|| {
+        let account_id = 1;
+        let kitty_id = 2;
+        // 预设余额
+        assert_ok!(Balances::set_balance(RuntimeOrigin::root(), account_id, ACCOUNT_BALANCE, 0));
+        assert_ok!(KittiesModule::create_kitty(RuntimeOrigin::signed(account_id), KITTY_NAME));
+        assert_ok!(KittiesModule::create_kitty(RuntimeOrigin::signed(account_id), KITTY_NAME));
+        assert_ok!(KittiesModule::commit_breed(RuntimeOrigin::signed(account_id), 0, 1, KITTY_NAME));
+
+        // reveal区块之后的区块hash不影响结果
+        run_to_block_with_hash(4, H256::repeat_byte(1));
+        run_to_block_with_hash(8, H256::repeat_byte(2));
+        assert_ok!(KittiesModule::reveal_breed(RuntimeOrigin::signed(account_id), kitty_id));
+        assert_eq!(KittiesModule::kitties(kitty_id).map(|kitty| kitty.dna), Some(dna));
+        assert_eq!(KittiesModule::reveal_seed(kitty_id), None);
+    });
+}
+
+/// 两阶段繁衍：待揭示期间父代被锁定，不能转移或出售
+#[test]
+fn reveal_breed_parents_locked() {
+    build_and_execute(|| {
+        let account_id = 1;
+        let recipient = 2;
+        let kitty_id = 2;
+        // 预设余额
+        assert_ok!(Balances::set_balance(RuntimeOrigin::root(), account_id, ACCOUNT_BALANCE, 0));
+        assert_ok!(KittiesModule::create_kitty(RuntimeOrigin::signed(account_id), KITTY_NAME));
+        assert_ok!(KittiesModule::create_kitty(RuntimeOrigin::signed(account_id), KITTY_NAME));
+        assert_ok!(KittiesModule::commit_breed(RuntimeOrigin::signed(account_id), 0, 1, KITTY_NAME));
+        assert_noop!(
+            KittiesModule::transfer(RuntimeOrigin::signed(account_id), recipient, 1),
+            Error::<Test>::KittyLocked
+        );
+
+        // 随机数确定后仍然锁定，无法通过转走父代取消繁衍
+        run_to_block(4);
+        assert!(KittiesModule::reveal_seed(kitty_id).is_some());
+        assert_noop!(
+            KittiesModule::transfer(RuntimeOrigin::signed(account_id), recipient, 1),
+            Error::<Test>::KittyLocked
+        );
+        assert_noop!(
+            KittiesModule::sale(RuntimeOrigin::signed(account_id), 0, None, None, None),
+            Error::<Test>::KittyLocked
+        );
+
+        // 揭示后解锁
+        assert_ok!(KittiesModule::reveal_breed(RuntimeOrigin::signed(recipient), kitty_id));
+        assert_eq!(KittiesModule::kitty_owner(kitty_id), Some(account_id));
+        assert_ok!(KittiesModule::transfer(RuntimeOrigin::signed(account_id), recipient, 1));
+    });
+}
+
+/// 两阶段繁衍：揭示时租借的父代已到期，取消且不退款
+#[test]
+fn reveal_breed_rental_expired_forfeits() {
+    build_and_execute(|| {
+        let owner = 1;
+        let borrower = 2;
+        let kitty_id = 2;
+        let fee = 100;
+        // 预设余额
+        assert_ok!(Balances::set_balance(RuntimeOrigin::root(), owner, ACCOUNT_BALANCE, 0));
+        assert_ok!(Balances::set_balance(RuntimeOrigin::root(), borrower, ACCOUNT_BALANCE, 0));
+        assert_ok!(KittiesModule::create_kitty(RuntimeOrigin::signed(owner), KITTY_NAME));
+        assert_ok!(KittiesModule::create_kitty(RuntimeOrigin::signed(borrower), KITTY_NAME));
+        assert_ok!(KittiesModule::lend(RuntimeOrigin::signed(owner), 0, borrower, 2, fee));
+        assert_ok!(KittiesModule::borrow(RuntimeOrigin::signed(borrower), 0));
+        assert_ok!(KittiesModule::commit_breed(RuntimeOrigin::signed(borrower), 0, 1, KITTY_NAME));
+        let pallet_balance = Balances::free_balance(&get_account_id());
+
+        run_to_block(4);
+        assert_eq!(KittiesModule::kitty_rental(0), None);
+        assert_ok!(KittiesModule::reveal_breed(RuntimeOrigin::signed(borrower), kitty_id));
+        System::assert_last_event(Event::BreedCancelled { who: borrower, kitty_id, refund: None }.into());
+        assert!(KittiesModule::kitties(kitty_id).is_none());
+        assert_eq!(KittiesModule::breed_commit(kitty_id), None);
+        assert_eq!(KittiesModule::reveal_seed(kitty_id), None);
+        assert_eq!(Balances::free_balance(borrower), ACCOUNT_BALANCE - 2 * KittyPrice::get() - fee);
+        assert_eq!(Balances::free_balance(&get_account_id()), pallet_balance);
+        assert_noop!(
+            KittiesModule::reveal_breed(RuntimeOrigin::signed(borrower), kitty_id),
+            Error::<Test>::NoBreedCommit
+        );
+    });
+}
+
+/// 白名单创建 Kitty
+#[test]
+fn create_kitty_allowlisted() {
+    build_and_execute(|| {
+        let account_id = 1;
+        let account_id_2 = 2;
+        let outsider = 3;
+        for who in [account_id, account_id_2, outsider] {
+            assert_ok!(Balances::set_balance(RuntimeOrigin::root(), who, ACCOUNT_BALANCE, 0));
+        }
+        // 构建白名单：账户1额度2，账户2额度1
+        let leaf_1 = KittiesModule::allowlist_leaf(&account_id, 2);
+        let leaf_2 = KittiesModule::allowlist_leaf(&account_id_2, 1);
+        let root = KittiesModule::allowlist_node(leaf_1, leaf_2);
+
+        // 未设置白名单
+        assert_noop!(
+            KittiesModule::create_kitty_allowlisted(RuntimeOrigin::signed(account_id), KITTY_NAME, 2, vec![leaf_2]),
+            Error::<Test>::NoAllowlist
+        );
+        // 只有root能设置
+        assert_noop!(
+            KittiesModule::set_allowlist_root(RuntimeOrigin::signed(account_id), Some(root)),
+            sp_runtime::DispatchError::BadOrigin
+        );
+        assert_ok!(KittiesModule::set_allowlist_root(RuntimeOrigin::root(), Some(root)));
+        System::assert_last_event(Event::AllowlistRootSet { root: Some(root) }.into());
+
+        // 额度与证明不符
+        assert_noop!(
+            KittiesModule::create_kitty_allowlisted(RuntimeOrigin::signed(account_id), KITTY_NAME, 3, vec![leaf_2]),
+            Error::<Test>::InvalidProof
+        );
+        // 不在白名单中
+        assert_noop!(
+            KittiesModule::create_kitty_allowlisted(RuntimeOrigin::signed(outsider), KITTY_NAME, 1, vec![leaf_2]),
+            Error::<Test>::InvalidProof
+        );
+
+        // 以优惠价格创建
+        assert_ok!(KittiesModule::create_kitty_allowlisted(RuntimeOrigin::signed(account_id), KITTY_NAME, 2, vec![leaf_2]));
+        assert_eq!(KittiesModule::kitty_owner(0), Some(account_id));
+        assert_eq!(Balances::free_balance(account_id), ACCOUNT_BALANCE - AllowlistPrice::get());
+        assert_eq!(Balances::free_balance(&get_account_id()), AllowlistPrice::get());
+        assert_ok!(KittiesModule::create_kitty_allowlisted(RuntimeOrigin::signed(account_id), KITTY_NAME, 2, vec![leaf_2]));
+        assert_eq!(KittiesModule::allowlist_claimed(account_id), 2);
+        // 额度用完
+        assert_noop!(
+            KittiesModule::create_kitty_allowlisted(RuntimeOrigin::signed(account_id), KITTY_NAME, 2, vec![leaf_2]),
+            Error::<Test>::AllowlistQuotaExceeded
+        );
+
+        // 另一个叶子的证明
+        assert_ok!(KittiesModule::create_kitty_allowlisted(RuntimeOrigin::signed(account_id_2), KITTY_NAME, 1, vec![leaf_1]));
+        assert_eq!(KittiesModule::kitty_owner(2), Some(account_id_2));
+    })
+}
+
+/// 管理员冻结、解冻 Kitty
+#[test]
+fn freeze_and_thaw_kitty() {
+    build_and_execute(|| {
+        let kitty_id = 0;
+        let account_id = 1;
+        let account_id_2 = 2;
+        // 预设余额
+        assert_ok!(Balances::set_balance(RuntimeOrigin::root(), account_id, ACCOUNT_BALANCE, 0));
+        assert_ok!(Balances::set_balance(RuntimeOrigin::root(), account_id_2, ACCOUNT_BALANCE, 0));
+        assert_ok!(KittiesModule::create_kitty(RuntimeOrigin::signed(account_id), KITTY_NAME));
+        assert_ok!(KittiesModule::create_kitty(RuntimeOrigin::signed(account_id), KITTY_NAME));
+        assert_ok!(KittiesModule::sale(RuntimeOrigin::signed(account_id), kitty_id, None, None, None));
+
+        // 只有管理员能冻结
+        assert_noop!(
+            KittiesModule::freeze_kitty(RuntimeOrigin::signed(account_id), kitty_id),
+            sp_runtime::DispatchError::BadOrigin
+        );
+        assert_ok!(KittiesModule::freeze_kitty(RuntimeOrigin::root(), kitty_id));
+        System::assert_last_event(Event::KittyFrozen { kitty_id }.into());
+        assert_noop!(
+            KittiesModule::freeze_kitty(RuntimeOrigin::root(), kitty_id),
+            Error::<Test>::KittyFrozen
+        );
+
+        // 冻结后不能转移、出售、购买和繁衍
+        assert_noop!(
+            KittiesModule::transfer(RuntimeOrigin::signed(account_id), account_id_2, kitty_id),
+            Error::<Test>::KittyFrozen
+        );
+        assert_ok!(KittiesModule::freeze_kitty(RuntimeOrigin::root(), 1));
+        assert_noop!(
+            KittiesModule::sale(RuntimeOrigin::signed(account_id), 1, None, None, None),
+            Error::<Test>::KittyFrozen
+        );
+        assert_noop!(
+            KittiesModule::buy(RuntimeOrigin::signed(account_id_2), kitty_id),
+            Error::<Test>::KittyFrozen
+        );
+        assert_noop!(
+            KittiesModule::breed(RuntimeOrigin::signed(account_id), kitty_id, 1, KITTY_NAME),
+            Error::<Test>::KittyFrozen
+        );
+
+        // 解冻后恢复
+        assert_ok!(KittiesModule::thaw_kitty(RuntimeOrigin::root(), 1));
+        assert_ok!(KittiesModule::sale(RuntimeOrigin::signed(account_id), 1, None, None, None));
+        assert_ok!(KittiesModule::thaw_kitty(RuntimeOrigin::root(), kitty_id));
+        System::assert_last_event(Event::KittyThawed { kitty_id }.into());
+        assert_noop!(
+            KittiesModule::thaw_kitty(RuntimeOrigin::root(), kitty_id),
+            Error::<Test>::NotFrozen
+        );
+        assert_ok!(KittiesModule::buy(RuntimeOrigin::signed(account_id_2), kitty_id));
+        assert_eq!(KittiesModule::kitty_owner(kitty_id), Some(account_id_2));
+    })
+}
+
+/// 管理员强制转移、下架、销毁 Kitty
+#[test]
+fn force_operations() {
+    build_and_execute(|| {
+        let kitty_id = 0;
+        let account_id = 1;
+        let account_id_2 = 2;
+        // 预设余额
+        assert_ok!(Balances::set_balance(RuntimeOrigin::root(), account_id, ACCOUNT_BALANCE, 0));
+        assert_ok!(KittiesModule::create_kitty(RuntimeOrigin::signed(account_id), KITTY_NAME));
+        assert_ok!(KittiesModule::offer_transfer(RuntimeOrigin::signed(account_id), kitty_id, account_id_2));
+
+        // 只有管理员能操作
+        assert_noop!(
+            KittiesModule::force_transfer(RuntimeOrigin::signed(account_id), kitty_id, account_id_2),
+            sp_runtime::DispatchError::BadOrigin
+        );
+        // 强制转移并清理待接收转移
+        assert_ok!(KittiesModule::force_transfer(RuntimeOrigin::root(), kitty_id, 3));
+        System::assert_last_event(Event::KittyForceTransferred {
+            kitty_id,
+            from: Some(account_id),
+            to: 3,
+        }.into());
+        assert_eq!(KittiesModule::kitty_owner(kitty_id), Some(3));
+        assert_eq!(KittiesModule::pending_transfer(kitty_id), None);
+
+        // 强制下架
+        assert_noop!(
+            KittiesModule::force_unlist(RuntimeOrigin::root(), kitty_id),
+            Error::<Test>::NotOnSale
+        );
+        assert_ok!(KittiesModule::sale(RuntimeOrigin::signed(3), kitty_id, None, None, None));
+        assert_ok!(KittiesModule::force_unlist(RuntimeOrigin::root(), kitty_id));
+        System::assert_last_event(Event::KittyForceUnlisted { kitty_id }.into());
+        assert!(KittiesModule::kitty_on_sale(kitty_id).is_none());
+
+        // 强制销毁
+        assert_ok!(KittiesModule::sale(RuntimeOrigin::signed(3), kitty_id, None, None, None));
+        assert_ok!(KittiesModule::force_burn(RuntimeOrigin::root(), kitty_id));
+        System::assert_last_event(Event::KittyBurned { kitty_id, owner: Some(3) }.into());
+        assert!(KittiesModule::kitties(kitty_id).is_none());
+        assert_eq!(KittiesModule::kitty_owner(kitty_id), None);
+        assert!(KittiesModule::kitty_on_sale(kitty_id).is_none());
+        assert_noop!(
+            KittiesModule::force_burn(RuntimeOrigin::root(), kitty_id),
+            Error::<Test>::InvalidKittyId
+        );
+    })
+}
+
+/// 借款、租借或质押中的kitty不能强制转移
+#[test]
+fn force_transfer_locked_kitty() {
+    build_and_execute(|| {
+        let account_id = 1;
+        let account_id_2 = 2;
+        // 预设余额
+        assert_ok!(Balances::set_balance(RuntimeOrigin::root(), account_id, ACCOUNT_BALANCE, 0));
+        assert_ok!(Balances::set_balance(RuntimeOrigin::root(), account_id_2, ACCOUNT_BALANCE, 0));
+        assert_ok!(KittiesModule::create_kitty(RuntimeOrigin::signed(account_id), KITTY_NAME));
+        assert_ok!(KittiesModule::create_kitty(RuntimeOrigin::signed(account_id), KITTY_NAME));
+        assert_ok!(KittiesModule::create_kitty(RuntimeOrigin::signed(account_id), KITTY_NAME));
+
+        // 借款中
+        assert_ok!(KittiesModule::request_loan(RuntimeOrigin::signed(account_id), 0, 2000, 10));
+        assert_ok!(KittiesModule::fund_loan(RuntimeOrigin::signed(account_id_2), 0));
+        assert_noop!(
+            KittiesModule::force_transfer(RuntimeOrigin::root(), 0, 3),
+            Error::<Test>::KittyLocked
+        );
+        // 租借中
+        assert_ok!(KittiesModule::lend(RuntimeOrigin::signed(account_id), 1, account_id_2, 5, 100));
+        assert_ok!(KittiesModule::borrow(RuntimeOrigin::signed(account_id_2), 1));
+        assert_noop!(
+            KittiesModule::force_transfer(RuntimeOrigin::root(), 1, 3),
+            Error::<Test>::KittyLocked
+        );
+        // 质押中
+        assert_ok!(KittiesModule::stake(RuntimeOrigin::signed(account_id), 2));
+        assert_noop!(
+            KittiesModule::force_transfer(RuntimeOrigin::root(), 2, 3),
+            Error::<Test>::KittyLocked
+        );
+
+        // 还款、到期、解除质押后可以强制转移
+        assert_ok!(KittiesModule::repay_loan(RuntimeOrigin::signed(account_id), 0));
+        assert_ok!(KittiesModule::force_transfer(RuntimeOrigin::root(), 0, 3));
+        run_to_block(6);
+        assert_ok!(KittiesModule::force_transfer(RuntimeOrigin::root(), 1, 3));
+        assert_ok!(KittiesModule::unstake(RuntimeOrigin::signed(account_id), 2));
+        assert_ok!(KittiesModule::force_transfer(RuntimeOrigin::root(), 2, 3));
+        assert_eq!(KittiesModule::kitty_owner(0), Some(3));
+        assert_eq!(KittiesModule::kitty_owner(1), Some(3));
+        assert_eq!(KittiesModule::kitty_owner(2), Some(3));
+    })
+}
+
+/// 强制销毁父代时，取消以其为父代的两阶段繁衍并退款
+#[test]
+fn force_burn_cancels_breed_commits() {
+    build_and_execute(|| {
+        let account_id = 1;
+        let kitty_id = 2;
+        // 预设余额
+        assert_ok!(Balances::set_balance(RuntimeOrigin::root(), account_id, ACCOUNT_BALANCE, 0));
+        assert_ok!(KittiesModule::create_kitty(RuntimeOrigin::signed(account_id), KITTY_NAME));
+        assert_ok!(KittiesModule::create_kitty(RuntimeOrigin::signed(account_id), KITTY_NAME));
+        assert_ok!(KittiesModule::commit_breed(RuntimeOrigin::signed(account_id), 0, 1, KITTY_NAME));
+        assert_eq!(Balances::free_balance(account_id), ACCOUNT_BALANCE - 3 * KittyPrice::get());
+
+        assert_ok!(KittiesModule::force_burn(RuntimeOrigin::root(), 0));
+        System::assert_has_event(
+            Event::BreedCancelled { who: account_id, kitty_id, refund: Some(KittyPrice::get()) }.into()
+        );
+        System::assert_last_event(Event::KittyBurned { kitty_id: 0, owner: Some(account_id) }.into());
+        assert_eq!(KittiesModule::breed_commit(kitty_id), None);
+        assert_eq!(Balances::free_balance(account_id), ACCOUNT_BALANCE - 2 * KittyPrice::get());
+
+        // 到达reveal区块也不会生成子kitty
+        run_to_block(4);
+        assert_eq!(KittiesModule::reveal_seed(kitty_id), None);
+        assert_noop!(
+            KittiesModule::reveal_breed(RuntimeOrigin::signed(account_id), kitty_id),
+            Error::<Test>::NoBreedCommit
+        );
+        assert!(KittiesModule::kitties(kitty_id).is_none());
+    });
+}
+
+/// 灵魂绑定 Kitty
+#[test]
+fn soulbound_kitty() {
+    build_and_execute(|| {
+        let kitty_id = 0;
+        let account_id = 1;
+        let account_id_2 = 2;
+        // 预设余额
+        assert_ok!(Balances::set_balance(RuntimeOrigin::root(), account_id, ACCOUNT_BALANCE, 0));
+        assert_ok!(Balances::set_balance(RuntimeOrigin::root(), account_id_2, ACCOUNT_BALANCE, 0));
+
+        // 只有root能创建
+        assert_noop!(
+            KittiesModule::mint_soulbound(RuntimeOrigin::signed(account_id), account_id, KITTY_NAME),
+            sp_runtime::DispatchError::BadOrigin
+        );
+        // 创建无需支付
+        assert_ok!(KittiesModule::mint_soulbound(RuntimeOrigin::root(), account_id, KITTY_NAME));
+        let kitty = KittiesModule::kitties(kitty_id).expect("Kitty Created");
+        assert!(kitty.soulbound);
+        System::assert_last_event(Event::KittyCreated { who: account_id, kitty_id, kitty }.into());
+        assert_eq!(KittiesModule::kitty_owner(kitty_id), Some(account_id));
+        assert_eq!(Balances::free_balance(account_id), ACCOUNT_BALANCE);
+
+        // 不能转移和出售
+        assert_noop!(
+            KittiesModule::transfer(RuntimeOrigin::signed(account_id), account_id_2, kitty_id),
+            Error::<Test>::Soulbound
+        );
+        assert_noop!(
+            KittiesModule::offer_transfer(RuntimeOrigin::signed(account_id), kitty_id, account_id_2),
+            Error::<Test>::Soulbound
+        );
+        assert_noop!(
+            KittiesModule::sale(RuntimeOrigin::signed(account_id), kitty_id, None, None, None),
+            Error::<Test>::Soulbound
+        );
+        // 即使已有在售记录也不能购买
+        crate::KittyOnSale::<Test>::insert(kitty_id, crate::Listing { expires_at: None, buyer: None, asset_price: None });
+        assert_noop!(
+            KittiesModule::buy(RuntimeOrigin::signed(account_id_2), kitty_id),
+            Error::<Test>::Soulbound
+        );
+        crate::KittyOnSale::<Test>::remove(kitty_id);
+
+        // 可以繁衍，子代不是灵魂绑定
+        assert_ok!(KittiesModule::create_kitty(RuntimeOrigin::signed(account_id), KITTY_NAME));
+        assert_ok!(KittiesModule::breed(RuntimeOrigin::signed(account_id), kitty_id, 1, KITTY_NAME));
+        assert!(!KittiesModule::kitties(2).unwrap().soulbound);
+        assert_ok!(KittiesModule::transfer(RuntimeOrigin::signed(account_id), account_id_2, 2));
+    })
+}
+
+/// 指定买家的私下出售
+#[test]
+fn private_sale() {
+    build_and_execute(|| {
+        let kitty_id = 0;
+        let seller = 1;
+        let buyer = 2;
+        let other = 3;
+        for who in [seller, buyer, other] {
+            assert_ok!(Balances::set_balance(RuntimeOrigin::root(), who, ACCOUNT_BALANCE, 0));
+        }
+        assert_ok!(KittiesModule::create_kitty(RuntimeOrigin::signed(seller), KITTY_NAME));
+        assert_ok!(KittiesModule::create_kitty(RuntimeOrigin::signed(seller), KITTY_NAME));
+
+        // 不能指定自己为买家
+        assert_noop!(
+            KittiesModule::sale(RuntimeOrigin::signed(seller), kitty_id, None, Some(seller), None),
+            Error::<Test>::CanNotTransferToSelf
+        );
+        assert_ok!(KittiesModule::sale(RuntimeOrigin::signed(seller), kitty_id, None, Some(buyer), None));
+        System::assert_last_event(Event::KittyOnSale { who: seller, kitty_id, buyer: Some(buyer), asset_price: None }.into());
+        assert!(KittiesModule::kitty_on_sale(kitty_id).unwrap().is_private());
+        assert_ok!(KittiesModule::sale(RuntimeOrigin::signed(seller), 1, None, None, None));
+
+        // 公开查询不包含私下出售
+        let public: Vec<_> = KittiesModule::public_listings(None, MAX_LISTINGS_PAGE).into_iter().map(|(id, _)| id).collect();
+        assert_eq!(public, vec![1]);
+
+        // 只有指定买家能购买
+        assert_noop!(
+            KittiesModule::buy(RuntimeOrigin::signed(other), kitty_id),
+            Error::<Test>::NotDesignatedBuyer
+        );
+        assert_ok!(KittiesModule::buy(RuntimeOrigin::signed(buyer), kitty_id));
+        assert_eq!(KittiesModule::kitty_owner(kitty_id), Some(buyer));
+        assert_ok!(KittiesModule::buy(RuntimeOrigin::signed(other), 1));
+        assert!(KittiesModule::public_listings(None, MAX_LISTINGS_PAGE).is_empty());
+    })
+}
+
+/// 分页查询公开在售
+#[test]
+fn public_listings_paginated() {
+    build_and_execute(|| {
+        let seller = 1;
+        // 预设余额
+        assert_ok!(Balances::set_balance(RuntimeOrigin::root(), seller, ACCOUNT_BALANCE, 0));
+        for kitty_id in 0..3 {
+            assert_ok!(KittiesModule::create_kitty(RuntimeOrigin::signed(seller), KITTY_NAME));
+            assert_ok!(KittiesModule::sale(RuntimeOrigin::signed(seller), kitty_id, None, None, None));
+        }
+
+        // 每页最多limit条，从上一页最后一条之后继续
+        let first: Vec<_> = KittiesModule::public_listings(None, 2).into_iter().map(|(id, _)| id).collect();
+        assert_eq!(first.len(), 2);
+        let second: Vec<_> = KittiesModule::public_listings(first.last().copied(), 2).into_iter().map(|(id, _)| id).collect();
+        assert_eq!(second.len(), 1);
+        let mut all = [first, second].concat();
+        all.sort();
+        assert_eq!(all, vec![0, 1, 2]);
+        assert!(KittiesModule::public_listings(None, 0).is_empty());
+    })
+}
+
+/// 打包出售 Kitty
+#[test]
+fn bundle_listing() {
+    build_and_execute(|| {
+        let seller = 1;
+        let buyer = 2;
+        let price = 8_000;
+        assert_ok!(Balances::set_balance(RuntimeOrigin::root(), seller, ACCOUNT_BALANCE, 0));
+        assert_ok!(Balances::set_balance(RuntimeOrigin::root(), buyer, ACCOUNT_BALANCE, 0));
+        assert_ok!(KittiesModule::batch_create(RuntimeOrigin::signed(seller), vec![KITTY_NAME; 4]));
+
+        // 数量不合法
+        assert_noop!(
+            KittiesModule::list_bundle(RuntimeOrigin::signed(seller), vec![0], price),
+            Error::<Test>::InvalidBundle
+        );
+        assert_noop!(
+            KittiesModule::list_bundle(RuntimeOrigin::signed(seller), vec![0, 1, 2, 3], price),
+            Error::<Test>::InvalidBundle
+        );
+        // 重复的kitty
+        assert_noop!(
+            KittiesModule::list_bundle(RuntimeOrigin::signed(seller), vec![0, 0], price),
+            Error::<Test>::AlreadyOnSale
+        );
+        // 已单独在售
+        assert_ok!(KittiesModule::sale(RuntimeOrigin::signed(seller), 3, None, None, None));
+        assert_noop!(
+            KittiesModule::list_bundle(RuntimeOrigin::signed(seller), vec![0, 3], price),
+            Error::<Test>::AlreadyOnSale
+        );
+
+        // 打包出售成功，成员不能再单独出售
+        assert_ok!(KittiesModule::list_bundle(RuntimeOrigin::signed(seller), vec![0, 1], price));
+        assert_eq!(KittiesModule::kitty_bundle(0), Some(0));
+        assert_noop!(
+            KittiesModule::sale(RuntimeOrigin::signed(seller), 0, None, None, None),
+            Error::<Test>::AlreadyOnSale
+        );
+        // 也不能出租或发起待接收的转移
+        assert_noop!(
+            KittiesModule::lend(RuntimeOrigin::signed(seller), 0, buyer, 5, 100),
+            Error::<Test>::AlreadyOnSale
+        );
+        assert_noop!(
+            KittiesModule::offer_transfer(RuntimeOrigin::signed(seller), 1, buyer),
+            Error::<Test>::AlreadyOnSale
+        );
+        assert_noop!(
+            KittiesModule::buy_bundle(RuntimeOrigin::signed(seller), 0),
+            Error::<Test>::AlreadyOwned
+        );
+
+        // 购买后全部转移
+        assert_ok!(KittiesModule::buy_bundle(RuntimeOrigin::signed(buyer), 0));
+        System::assert_last_event(Event::BundleBought { who: buyer, bundle_id: 0 }.into());
+        assert_eq!(KittiesModule::kitty_owner(0), Some(buyer));
+        assert_eq!(KittiesModule::kitty_owner(1), Some(buyer));
+        assert_eq!(KittiesModule::kitty_bundle(0), None);
+        assert_eq!(Balances::free_balance(seller), ACCOUNT_BALANCE - 4 * KittyPrice::get() + price);
+        assert_eq!(Balances::free_balance(buyer), ACCOUNT_BALANCE - price);
+        assert_noop!(
+            KittiesModule::buy_bundle(RuntimeOrigin::signed(buyer), 0),
+            Error::<Test>::NoBundle
+        );
+    })
+}
+
+/// 打包出售的撤销与失效
+#[test]
+fn bundle_cancel_and_invalidate() {
+    build_and_execute(|| {
+        let seller = 1;
+        let buyer = 2;
+        assert_ok!(Balances::set_balance(RuntimeOrigin::root(), seller, ACCOUNT_BALANCE, 0));
+        assert_ok!(KittiesModule::batch_create(RuntimeOrigin::signed(seller), vec![KITTY_NAME; 2]));
+
+        // 撤销
+        assert_ok!(KittiesModule::list_bundle(RuntimeOrigin::signed(seller), vec![0, 1], 1_000));
+        assert_noop!(
+            KittiesModule::cancel_bundle(RuntimeOrigin::signed(buyer), 0),
+            Error::<Test>::NotOwner
+        );
+        assert_ok!(KittiesModule::cancel_bundle(RuntimeOrigin::signed(seller), 0));
+        System::assert_last_event(Event::BundleCancelled { who: seller, bundle_id: 0 }.into());
+        assert_eq!(KittiesModule::bundles(0), None);
+        assert_eq!(KittiesModule::kitty_bundle(0), None);
+
+        // 任一成员转移后失效
+        assert_ok!(KittiesModule::list_bundle(RuntimeOrigin::signed(seller), vec![0, 1], 1_000));
+        assert_ok!(KittiesModule::transfer(RuntimeOrigin::signed(seller), buyer, 1));
+        System::assert_has_event(Event::BundleInvalidated { bundle_id: 1 }.into());
+        assert_eq!(KittiesModule::bundles(1), None);
+        assert_eq!(KittiesModule::kitty_bundle(0), None);
+        assert_noop!(
+            KittiesModule::buy_bundle(RuntimeOrigin::signed(buyer), 1),
+            Error::<Test>::NoBundle
+        );
+    })
+}
+
+/// 多实例：两个合集的存储、价格和资金账户互不影响
+#[test]
+fn instances_are_isolated() {
+    build_and_execute(|| {
+        let account_id = 1;
+        assert_ok!(Balances::set_balance(RuntimeOrigin::root(), account_id, ACCOUNT_BALANCE, 0));
+
+        // 在默认合集中创建两只
+        assert_ok!(KittiesModule::create_kitty(RuntimeOrigin::signed(account_id), KITTY_NAME));
+        assert_ok!(KittiesModule::create_kitty(RuntimeOrigin::signed(account_id), KITTY_NAME));
+        // 第二个合集从0开始编号
+        assert_eq!(KittiesCollection2::next_kitty_id(), 0);
+        assert_ok!(KittiesCollection2::create_kitty(RuntimeOrigin::signed(account_id), KITTY_NAME));
+        System::assert_last_event(crate::Event::<Test, Instance1>::KittyCreated {
+            who: account_id,
+            kitty_id: 0,
+            kitty: KittiesCollection2::kitties(0).expect("Kitty Created"),
+        }.into());
+
+        assert_eq!(KittiesModule::next_kitty_id(), 2);
+        assert_eq!(KittiesCollection2::next_kitty_id(), 1);
+        // 价格分别计入各自的资金账户
+        assert_eq!(
+            Balances::free_balance(account_id),
+            ACCOUNT_BALANCE - KittyPrice::get() * 2 - Collection2Price::get()
+        );
+        assert_eq!(Balances::free_balance(&get_account_id()), KittyPrice::get() * 2);
+        assert_eq!(Balances::free_balance(&get_collection2_account_id()), Collection2Price::get());
+
+        // 转移第二个合集的kitty不影响默认合集的同ID kitty
+        assert_ok!(KittiesCollection2::transfer(RuntimeOrigin::signed(account_id), 2, 0));
+        assert_eq!(KittiesCollection2::kitty_owner(0), Some(2));
+        assert_eq!(KittiesModule::kitty_owner(0), Some(account_id));
+        assert_noop!(
+            KittiesCollection2::transfer(RuntimeOrigin::signed(account_id), 2, 1),
+            Error::<Test, Instance1>::InvalidKittyId
+        );
+    });
+}
+
+/// 唯一名称登记
+#[test]
+fn unique_names() {
+    build_and_execute(|| {
+        let account_id = 1;
+        let account_id_2 = 2;
+        let new_name = *b"kitty002";
+        // 预设余额
+        assert_ok!(Balances::set_balance(RuntimeOrigin::root(), account_id, ACCOUNT_BALANCE, 0));
+        assert_ok!(Balances::set_balance(RuntimeOrigin::root(), account_id_2, ACCOUNT_BALANCE, 0));
+        // 两只同名的kitty
+        assert_ok!(KittiesModule::create_kitty(RuntimeOrigin::signed(account_id), KITTY_NAME));
+        assert_ok!(KittiesModule::create_kitty(RuntimeOrigin::signed(account_id_2), KITTY_NAME));
+
+        // 只有owner能登记
+        assert_noop!(
+            KittiesModule::claim_name(RuntimeOrigin::signed(account_id_2), 0),
+            Error::<Test>::NotOwner
+        );
+        assert_ok!(KittiesModule::claim_name(RuntimeOrigin::signed(account_id), 0));
+        System::assert_last_event(Event::NameClaimed { who: account_id, kitty_id: 0, name: KITTY_NAME }.into());
+        assert_eq!(KittiesModule::name_to_kitty(KITTY_NAME), Some(0));
+        // 名称只能被一个kitty登记
+        assert_noop!(
+            KittiesModule::claim_name(RuntimeOrigin::signed(account_id_2), 1),
+            Error::<Test>::NameTaken
+        );
+        // 不能改名为已被登记的名称
+        assert_ok!(KittiesModule::rename(RuntimeOrigin::signed(account_id_2), 1, new_name));
+        assert_ok!(KittiesModule::claim_name(RuntimeOrigin::signed(account_id_2), 1));
+        assert_noop!(
+            KittiesModule::rename(RuntimeOrigin::signed(account_id), 0, new_name),
+            Error::<Test>::NameTaken
+        );
+
+        // 改名时登记随之变更，旧名称被释放
+        assert_ok!(KittiesModule::rename(RuntimeOrigin::signed(account_id), 0, *b"kitty001"));
+        System::assert_last_event(Event::KittyRenamed { who: account_id, kitty_id: 0, name: *b"kitty001" }.into());
+        assert_eq!(KittiesModule::kitties(0).map(|kitty| kitty.name), Some(*b"kitty001"));
+        assert_eq!(KittiesModule::name_to_kitty(KITTY_NAME), None);
+        assert_eq!(KittiesModule::name_to_kitty(*b"kitty001"), Some(0));
+
+        // 主动释放
+        assert_ok!(KittiesModule::release_name(RuntimeOrigin::signed(account_id), 0));
+        System::assert_last_event(Event::NameReleased { kitty_id: 0, name: *b"kitty001" }.into());
+        assert_noop!(
+            KittiesModule::release_name(RuntimeOrigin::signed(account_id), 0),
+            Error::<Test>::NameNotClaimed
+        );
+
+        // 销毁时释放
+        assert_ok!(KittiesModule::force_burn(RuntimeOrigin::root(), 1));
+        assert_eq!(KittiesModule::name_to_kitty(new_name), None);
+    });
+}
+
+/// 链下签名订单
+#[test]
+fn fill_signed_order() {
+    build_and_execute(|| {
+        let kitty_id = 0;
+        let seller = 1;
+        let buyer = 2;
+        let price = 3000;
+        // 预设余额
+        assert_ok!(Balances::set_balance(RuntimeOrigin::root(), seller, ACCOUNT_BALANCE, 0));
+        assert_ok!(Balances::set_balance(RuntimeOrigin::root(), buyer, ACCOUNT_BALANCE, 0));
+        assert_ok!(KittiesModule::create_kitty(RuntimeOrigin::signed(seller), KITTY_NAME));
+
+        let order = crate::Order { kitty_id, price, expires_at: 10, nonce: 0 };
+        let payload = KittiesModule::order_payload(&order);
+        // 签名者必须是owner
+        assert_noop!(
+            KittiesModule::fill_order(RuntimeOrigin::signed(buyer), order, TestSignature(buyer, payload.clone())),
+            Error::<Test>::InvalidSignature
+        );
+        // 签名内容必须与订单一致
+        let other = crate::Order { price: 1, ..order };
+        assert_noop!(
+            KittiesModule::fill_order(RuntimeOrigin::signed(buyer), other, TestSignature(seller, payload.clone())),
+            Error::<Test>::InvalidSignature
+        );
+
+        // 其他链或其他合集的签名不能使用
+        let genesis_hash = System::block_hash(0);
+        let other_chain = (H256::repeat_byte(1), KittyPalletId::get(), order).encode();
+        assert_noop!(
+            KittiesModule::fill_order(RuntimeOrigin::signed(buyer), order, TestSignature(seller, other_chain)),
+            Error::<Test>::InvalidSignature
+        );
+        let other_collection = (genesis_hash, Collection2PalletId::get(), order).encode();
+        assert_noop!(
+            KittiesModule::fill_order(RuntimeOrigin::signed(buyer), order, TestSignature(seller, other_collection)),
+            Error::<Test>::InvalidSignature
+        );
+        assert_eq!(payload, (genesis_hash, KittyPalletId::get(), order).encode());
+
+        assert_ok!(KittiesModule::fill_order(RuntimeOrigin::signed(buyer), order, TestSignature(seller, payload.clone())));
+        System::assert_last_event(Event::OrderFilled { who: buyer, seller, kitty_id, price, nonce: 0 }.into());
+        assert_eq!(KittiesModule::kitty_owner(kitty_id), Some(buyer));
+        assert_eq!(Balances::free_balance(seller), ACCOUNT_BALANCE - KittyPrice::get() + price);
+        assert_eq!(Balances::free_balance(buyer), ACCOUNT_BALANCE - price);
+        assert_eq!(KittiesModule::order_used(seller, 0), Some(()));
+    });
+}
+
+/// 链下签名订单 - 过期、重放和撤销
+#[test]
+fn expire_and_cancel_signed_order() {
+    build_and_execute(|| {
+        let kitty_id = 0;
+        let seller = 1;
+        let buyer = 2;
+        // 预设余额
+        assert_ok!(Balances::set_balance(RuntimeOrigin::root(), seller, ACCOUNT_BALANCE, 0));
+        assert_ok!(Balances::set_balance(RuntimeOrigin::root(), buyer, ACCOUNT_BALANCE, 0));
+        assert_ok!(KittiesModule::create_kitty(RuntimeOrigin::signed(seller), KITTY_NAME));
+        let sign = |order: &crate::Order<u128, u64>| TestSignature(seller, KittiesModule::order_payload(order));
+
+        // 过期的订单不能成交
+        let order = crate::Order { kitty_id, price: 1000, expires_at: 5, nonce: 0 };
+        run_to_block(5);
+        assert_noop!(
+            KittiesModule::fill_order(RuntimeOrigin::signed(buyer), order, sign(&order)),
+            Error::<Test>::OrderExpired
+        );
+
+        // 撤销序号不大于1的订单
+        assert_ok!(KittiesModule::cancel_orders_up_to(RuntimeOrigin::signed(seller), 1));
+        System::assert_last_event(Event::OrdersCancelled { who: seller, min_nonce: 2 }.into());
+        assert_noop!(
+            KittiesModule::cancel_orders_up_to(RuntimeOrigin::signed(seller), 0),
+            Error::<Test>::InvalidNonce
+        );
+        let order = crate::Order { kitty_id, price: 1000, expires_at: 20, nonce: 1 };
+        assert_noop!(
+            KittiesModule::fill_order(RuntimeOrigin::signed(buyer), order, sign(&order)),
+            Error::<Test>::OrderUsed
+        );
+
+        // 成交后订单不能重放，即使kitty回到卖家手中
+        let order = crate::Order { kitty_id, price: 1000, expires_at: 20, nonce: 2 };
+        assert_ok!(KittiesModule::fill_order(RuntimeOrigin::signed(buyer), order, sign(&order)));
+        assert_ok!(KittiesModule::transfer(RuntimeOrigin::signed(buyer), seller, kitty_id));
+        assert_noop!(
+            KittiesModule::fill_order(RuntimeOrigin::signed(buyer), order, sign(&order)),
+            Error::<Test>::OrderUsed
+        );
+    });
+}
+
+/// 战斗结果由属性和随机数决定
+#[test]
+fn resolve_battle() {
+    let strong = crate::KittyStats { attack: 200, defense: 200, speed: 100 };
+    let weak = crate::KittyStats { attack: 10, defense: 10, speed: 10 };
+    assert_eq!(crate::KittyStats::from_dna(&[0, 1, 2, 3, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]),
+               crate::KittyStats { attack: 1, defense: 2, speed: 3 });
+
+    assert!(KittiesModule::resolve_battle(&strong, &weak, [0u8; 16]));
+    assert!(!KittiesModule::resolve_battle(&weak, &strong, [0u8; 16]));
+    // 随机数可以逆转结果
+    let mut roll = [0u8; 16];
+    roll[0] = 255;
+    assert!(KittiesModule::resolve_battle(&weak, &crate::KittyStats { attack: 0, defense: 100, speed: 40 }, roll));
+    // 平局由守方获胜
+    assert!(!KittiesModule::resolve_battle(&weak, &weak, [0u8; 16]));
+}
+
+/// Kitty战斗
+#[test]
+fn battle_kitty() {
+    build_and_execute(|| {
+        let account_id = 1;
+        let account_id_2 = 2;
+        let stake = BattleStake::get();
+        let reward = BattleReward::get();
+        // 预设余额
+        assert_ok!(Balances::set_balance(RuntimeOrigin::root(), account_id, ACCOUNT_BALANCE, 0));
+        assert_ok!(Balances::set_balance(RuntimeOrigin::root(), account_id_2, ACCOUNT_BALANCE, 0));
+        assert_ok!(KittiesModule::create_kitty(RuntimeOrigin::signed(account_id), KITTY_NAME));
+        assert_ok!(KittiesModule::create_kitty(RuntimeOrigin::signed(account_id), KITTY_NAME));
+        assert_ok!(KittiesModule::create_kitty(RuntimeOrigin::signed(account_id_2), KITTY_NAME));
+
+        // 只能用自己的kitty挑战其他账户的kitty
+        assert_noop!(
+            KittiesModule::challenge(RuntimeOrigin::signed(account_id_2), 0, 2),
+            Error::<Test>::NotOwner
+        );
+        assert_noop!(
+            KittiesModule::challenge(RuntimeOrigin::signed(account_id), 0, 1),
+            Error::<Test>::CanNotBattleSelf
+        );
+
+        // 发起挑战时押注，撤销后退还
+        assert_ok!(KittiesModule::challenge(RuntimeOrigin::signed(account_id), 0, 2));
+        System::assert_last_event(Event::ChallengeIssued { who: account_id, kitty_id: 0, opponent_id: 2 }.into());
+        assert_eq!(Balances::free_balance(account_id), ACCOUNT_BALANCE - 2 * KittyPrice::get() - stake);
+        assert_noop!(
+            KittiesModule::challenge(RuntimeOrigin::signed(account_id), 0, 2),
+            Error::<Test>::ChallengeExists
+        );
+        assert_noop!(
+            KittiesModule::accept_challenge(RuntimeOrigin::signed(account_id), 0),
+            Error::<Test>::NotOwner
+        );
+        assert_noop!(
+            KittiesModule::cancel_challenge(RuntimeOrigin::signed(account_id_2), 0),
+            Error::<Test>::NotOwner
+        );
+        assert_ok!(KittiesModule::cancel_challenge(RuntimeOrigin::signed(account_id), 0));
+        System::assert_last_event(Event::ChallengeCancelled { who: account_id, kitty_id: 0 }.into());
+        assert_eq!(Balances::free_balance(account_id), ACCOUNT_BALANCE - 2 * KittyPrice::get());
+
+        // 对方接受并押注，双方进入冷却
+        assert_ok!(KittiesModule::challenge(RuntimeOrigin::signed(account_id), 0, 2));
+        assert_ok!(KittiesModule::accept_challenge(RuntimeOrigin::signed(account_id_2), 0));
+        System::assert_last_event(
+            Event::ChallengeAccepted { who: account_id_2, kitty_id: 0, opponent_id: 2, fight_at: 4 }.into()
+        );
+        assert_eq!(KittiesModule::battle_cooldown(0), Some(9));
+        assert_eq!(KittiesModule::battle_cooldown(2), Some(9));
+        assert_noop!(
+            KittiesModule::accept_challenge(RuntimeOrigin::signed(account_id_2), 0),
+            Error::<Test>::ChallengeAlreadyAccepted
+        );
+        assert_noop!(
+            KittiesModule::cancel_challenge(RuntimeOrigin::signed(account_id), 0),
+            Error::<Test>::ChallengeAlreadyAccepted
+        );
+        assert_noop!(
+            KittiesModule::challenge(RuntimeOrigin::signed(account_id), 1, 2),
+            Error::<Test>::KittyInCooldown
+        );
+
+        // 在战斗区块进行
+        run_to_block(3);
+        assert_eq!(KittiesModule::battle_record(0), (0, 0));
+        run_to_block(4);
+        let (winner, loser, winner_account, loser_account) = if KittiesModule::battle_record(0) == (1, 0) {
+            (0, 2, account_id, account_id_2)
+        } else {
+            (2, 0, account_id_2, account_id)
+        };
+        System::assert_last_event(Event::KittyBattled {
+            who: account_id,
+            kitty_id: 0,
+            opponent_id: 2,
+            winner,
+            reward: Some(reward),
+        }.into());
+        assert_eq!(KittiesModule::battle_record(winner), (1, 0));
+        assert_eq!(KittiesModule::battle_record(loser), (0, 1));
+        assert_eq!(KittiesModule::battle_challenge(0), None);
+        // 胜者取回押注并获得奖励，败者的押注归pallet账户
+        let minted = |who| if who == account_id { 2 } else { 1 };
+        assert_eq!(Balances::free_balance(winner_account), ACCOUNT_BALANCE - KittyPrice::get() * minted(winner_account) + reward);
+        assert_eq!(Balances::free_balance(loser_account), ACCOUNT_BALANCE - KittyPrice::get() * minted(loser_account) - stake);
+        assert_eq!(Balances::free_balance(&get_account_id()), KittyPrice::get() * 3 + stake - reward);
+
+        // 冷却结束前不能战斗
+        assert_noop!(
+            KittiesModule::challenge(RuntimeOrigin::signed(account_id), 0, 2),
+            Error::<Test>::KittyInCooldown
+        );
+        run_to_block(9);
+        assert_ok!(KittiesModule::challenge(RuntimeOrigin::signed(account_id), 0, 2));
+    });
+}
+
+/// 反复战斗不能消耗pallet账户的余额
+#[test]
+fn battle_cannot_drain_pallet() {
+    build_and_execute(|| {
+        let account_id = 1;
+        let account_id_2 = 2;
+        // 预设余额
+        assert_ok!(Balances::set_balance(RuntimeOrigin::root(), account_id, ACCOUNT_BALANCE, 0));
+        assert_ok!(Balances::set_balance(RuntimeOrigin::root(), account_id_2, ACCOUNT_BALANCE, 0));
+        assert_ok!(KittiesModule::create_kitty(RuntimeOrigin::signed(account_id), KITTY_NAME));
+        assert_ok!(KittiesModule::create_kitty(RuntimeOrigin::signed(account_id_2), KITTY_NAME));
+        let pallet_balance = Balances::free_balance(&get_account_id());
+        let total = Balances::free_balance(account_id) + Balances::free_balance(account_id_2);
+        let retained = BattleStake::get() - BattleReward::get();
+
+        // 双方轮流挑战，每场pallet账户只增加败者押注与奖励的差额
+        for round in 0..10u64 {
+            let (challenger, kitty_id, opponent, opponent_id) = if round % 2 == 0 {
+                (account_id, 0, account_id_2, 1)
+            } else {
+                (account_id_2, 1, account_id, 0)
+            };
+            assert_ok!(KittiesModule::challenge(RuntimeOrigin::signed(challenger), kitty_id, opponent_id));
+            assert_ok!(KittiesModule::accept_challenge(RuntimeOrigin::signed(opponent), kitty_id));
+            run_to_block(KittiesModule::battle_cooldown(kitty_id).unwrap());
+
+            let battles = (round + 1) as u128;
+            assert_eq!(Balances::free_balance(&get_account_id()), pallet_balance + retained * battles);
+            assert_eq!(Balances::free_balance(account_id) + Balances::free_balance(account_id_2), total - retained * battles);
+        }
+        let (wins, losses) = KittiesModule::battle_record(0);
+        assert_eq!(wins + losses, 10);
+    });
+}
+
+/// 喂养升级和dna变异
+#[test]
+fn feed_and_mutate_kitty() {
+    build_and_execute(|| {
+        let kitty_id = 0;
+        let account_id = 1;
+        // 预设余额
+        assert_ok!(Balances::set_balance(RuntimeOrigin::root(), account_id, ACCOUNT_BALANCE, 0));
+        assert_ok!(KittiesModule::create_kitty(RuntimeOrigin::signed(account_id), KITTY_NAME));
+        let kitty = KittiesModule::kitties(kitty_id).expect("Kitty Created");
+        assert_eq!((kitty.experience, kitty.level, kitty.mutated), (0, 0, false));
+
+        assert_noop!(
+            KittiesModule::feed(RuntimeOrigin::signed(account_id), kitty_id, 0),
+            Error::<Test>::InvalidFeedCount
+        );
+        // 等级不足不能变异
+        assert_noop!(
+            KittiesModule::mutate(RuntimeOrigin::signed(account_id), kitty_id),
+            Error::<Test>::LevelTooLow
+        );
+
+        // 喂养25次，获得250经验，升到2级
+        assert_ok!(KittiesModule::feed(RuntimeOrigin::signed(account_id), kitty_id, 25));
+        System::assert_last_event(Event::KittyFed { who: account_id, kitty_id, experience: 250, level: 2 }.into());
+        assert_eq!(Balances::free_balance(account_id), ACCOUNT_BALANCE - KittyPrice::get() - FeedPrice::get() * 25);
+        assert_eq!(Balances::free_balance(&get_account_id()), KittyPrice::get() + FeedPrice::get() * 25);
+        assert_noop!(
+            KittiesModule::mutate(RuntimeOrigin::signed(account_id), kitty_id),
+            Error::<Test>::LevelTooLow
+        );
+
+        // 升到3级后可以支付并提交一次变异
+        assert_ok!(KittiesModule::feed(RuntimeOrigin::signed(account_id), kitty_id, 5));
+        assert_ok!(KittiesModule::mutate(RuntimeOrigin::signed(account_id), kitty_id));
+        System::assert_last_event(Event::MutationCommitted { who: account_id, kitty_id, reveal_at: 4 }.into());
+        assert_eq!(
+            Balances::free_balance(account_id),
+            ACCOUNT_BALANCE - KittyPrice::get() - FeedPrice::get() * 30 - MutationPrice::get()
+        );
+        assert_eq!(KittiesModule::mutation_commit(kitty_id), Some((account_id, 4)));
+        assert_eq!(KittiesModule::kitties(kitty_id).map(|kitty| kitty.dna), Some(kitty.dna));
+        // 生效前不能重复提交，也不能转移
+        assert_noop!(
+            KittiesModule::mutate(RuntimeOrigin::signed(account_id), kitty_id),
+            Error::<Test>::MutationPending
+        );
+        assert_noop!(
+            KittiesModule::transfer(RuntimeOrigin::signed(account_id), 2, kitty_id),
+            Error::<Test>::KittyLocked
+        );
+
+        // 在reveal区块生效
+        run_to_block(3);
+        assert!(!KittiesModule::kitties(kitty_id).expect("Kitty Exists").mutated);
+        run_to_block(4);
+        let mutated = KittiesModule::kitties(kitty_id).expect("Kitty Mutated");
+        System::assert_last_event(Event::KittyMutated { who: account_id, kitty_id, dna: mutated.dna }.into());
+        assert_eq!(mutated.level, 3);
+        assert!(mutated.mutated);
+        assert_ne!(mutated.dna, kitty.dna);
+        assert_eq!(mutated.dna.iter().zip(kitty.dna.iter()).filter(|(a, b)| a != b).count(), 1);
+        assert_eq!(KittiesModule::mutation_commit(kitty_id), None);
+        assert_noop!(
+            KittiesModule::mutate(RuntimeOrigin::signed(account_id), kitty_id),
+            Error::<Test>::AlreadyMutated
+        );
+        assert_ok!(KittiesModule::transfer(RuntimeOrigin::signed(account_id), 2, kitty_id));
+    });
+}
+
+/// 提交变异并推进到生效区块，返回变异后的dna
+fn commit_and_mutate(parent_hash: H256) -> [u8; 16] {
+    let mut dna = [0u8; 16];
+    build_and_execute(|| {
+        let kitty_id = 0;
+        let account_id = 1;
+        // 预设余额
+        assert_ok!(Balances::set_balance(RuntimeOrigin::root(), account_id, ACCOUNT_BALANCE, 0));
+        assert_ok!(KittiesModule::create_kitty(RuntimeOrigin::signed(account_id), KITTY_NAME));
+        assert_ok!(KittiesModule::feed(RuntimeOrigin::signed(account_id), kitty_id, 30));
+        assert_ok!(KittiesModule::mutate(RuntimeOrigin::signed(account_id), kitty_id));
+        run_to_block_with_hash(4, parent_hash);
+        dna = KittiesModule::kitties(kitty_id).expect("Kitty Mutated").dna;
+    });
+    dna
+}
+
+/// 变异结果由提交之后的区块决定，提交时无法预知
+#[test]
+fn mutate_uses_later_randomness() {
+    assert_ne!(commit_and_mutate(H256::repeat_byte(1)), commit_and_mutate(H256::repeat_byte(2)));
+    assert_eq!(commit_and_mutate(H256::repeat_byte(1)), commit_and_mutate(H256::repeat_byte(1)));
+}
+
+/// 稀有度权重
+#[test]
+fn rarity_weight() {
+    let mut dna = [0u8; 16];
+    assert_eq!(KittiesModule::rarity_weight(&dna), 1);
+    dna[0] = 180;
+    assert_eq!(KittiesModule::rarity_weight(&dna), 2);
+    dna[0] = 230;
+    assert_eq!(KittiesModule::rarity_weight(&dna), 5);
+    dna[0] = 255;
+    assert_eq!(KittiesModule::rarity_weight(&dna), 10);
+}
+
+/// Kitty质押
+#[test]
+fn stake_kitty() {
+    build_and_execute(|| {
+        let kitty_id = 0;
+        let account_id = 1;
+        let account_id_2 = 2;
+        // 预设余额
+        assert_ok!(Balances::set_balance(RuntimeOrigin::root(), account_id, ACCOUNT_BALANCE, 0));
+        assert_ok!(KittiesModule::create_kitty(RuntimeOrigin::signed(account_id), KITTY_NAME));
+        // 固定稀有度，并达到可变异的等级
+        crate::Kitties::<Test>::mutate(kitty_id, |kitty| {
+            let kitty = kitty.as_mut().unwrap();
+            kitty.dna[0] = 230;
+            kitty.level = 3;
+        });
+
+        assert_noop!(
+            KittiesModule::stake(RuntimeOrigin::signed(account_id_2), kitty_id),
+            Error::<Test>::NotOwner
+        );
+        assert_ok!(KittiesModule::stake(RuntimeOrigin::signed(account_id), kitty_id));
+        System::assert_last_event(Event::KittyStaked { who: account_id, kitty_id }.into());
+        assert_noop!(
+            KittiesModule::stake(RuntimeOrigin::signed(account_id), kitty_id),
+            Error::<Test>::KittyLocked
+        );
+        // 质押中不能转移和出售
+        assert_noop!(
+            KittiesModule::transfer(RuntimeOrigin::signed(account_id), account_id_2, kitty_id),
+            Error::<Test>::KittyLocked
+        );
+        assert_noop!(
+            KittiesModule::sale(RuntimeOrigin::signed(account_id), kitty_id, None, None, None),
+            Error::<Test>::KittyLocked
+        );
+        // 质押中不能变异改变稀有度
+        assert_noop!(
+            KittiesModule::mutate(RuntimeOrigin::signed(account_id), kitty_id),
+            Error::<Test>::KittyLocked
+        );
+
+        // 10个区块 * 每区块10 * 稀有度5
+        run_to_block(11);
+        assert_eq!(KittiesModule::pending_reward(kitty_id), 500);
+        assert_noop!(
+            KittiesModule::claim_staking_reward(RuntimeOrigin::signed(account_id_2), kitty_id),
+            Error::<Test>::NotOwner
+        );
+        assert_ok!(KittiesModule::claim_staking_reward(RuntimeOrigin::signed(account_id), kitty_id));
+        System::assert_last_event(Event::StakingRewardClaimed { who: account_id, kitty_id, reward: 500 }.into());
+        assert_eq!(Balances::free_balance(account_id), ACCOUNT_BALANCE - KittyPrice::get() + 500);
+        assert_eq!(KittiesModule::pending_reward(kitty_id), 0);
+
+        // 解除质押时结算剩余奖励
+        run_to_block(13);
+        assert_ok!(KittiesModule::unstake(RuntimeOrigin::signed(account_id), kitty_id));
+        System::assert_last_event(Event::KittyUnstaked { who: account_id, kitty_id, reward: Some(100) }.into());
+        assert_eq!(Balances::free_balance(account_id), ACCOUNT_BALANCE - KittyPrice::get() + 600);
+        assert_noop!(
+            KittiesModule::unstake(RuntimeOrigin::signed(account_id), kitty_id),
+            Error::<Test>::NotStaked
+        );
+        // 待变异期间不能转移，生效后解锁
+        assert_ok!(KittiesModule::mutate(RuntimeOrigin::signed(account_id), kitty_id));
+        assert_noop!(
+            KittiesModule::transfer(RuntimeOrigin::signed(account_id), account_id_2, kitty_id),
+            Error::<Test>::KittyLocked
+        );
+        run_to_block(16);
+        assert!(KittiesModule::kitties(kitty_id).expect("Kitty Mutated").mutated);
+        assert_ok!(KittiesModule::transfer(RuntimeOrigin::signed(account_id), account_id_2, kitty_id));
+    });
+}
+
+/// Kitty抵押借款并还款
+#[test]
+fn kitty_loan_repaid() {
+    build_and_execute(|| {
+        let kitty_id = 0;
+        let borrower = 1;
+        let lender = 2;
+        let amount = 2000;
+        // 预设余额
+        assert_ok!(Balances::set_balance(RuntimeOrigin::root(), borrower, ACCOUNT_BALANCE, 0));
+        assert_ok!(Balances::set_balance(RuntimeOrigin::root(), lender, ACCOUNT_BALANCE, 0));
+        assert_ok!(KittiesModule::create_kitty(RuntimeOrigin::signed(borrower), KITTY_NAME));
+
+        // 借款不能超过抵押额度
+        assert_noop!(
+            KittiesModule::request_loan(RuntimeOrigin::signed(borrower), kitty_id, KittyPrice::get() / 2 + 1, 10),
+            Error::<Test>::InvalidLoanAmount
+        );
+        assert_ok!(KittiesModule::request_loan(RuntimeOrigin::signed(borrower), kitty_id, amount, 10));
+        System::assert_last_event(Event::LoanRequested { who: borrower, kitty_id, amount, duration: 10 }.into());
+        // 请求期间kitty被锁定
+        assert_noop!(
+            KittiesModule::transfer(RuntimeOrigin::signed(borrower), lender, kitty_id),
+            Error::<Test>::KittyLocked
+        );
+
+        // 被冻结的kitty不能作为抵押
+        assert_ok!(KittiesModule::freeze_kitty(RuntimeOrigin::root(), kitty_id));
+        assert_noop!(
+            KittiesModule::fund_loan(RuntimeOrigin::signed(lender), kitty_id),
+            Error::<Test>::KittyFrozen
+        );
+        assert_ok!(KittiesModule::thaw_kitty(RuntimeOrigin::root(), kitty_id));
+
+        assert_ok!(KittiesModule::fund_loan(RuntimeOrigin::signed(lender), kitty_id));
+        System::assert_last_event(Event::LoanFunded { lender, borrower, kitty_id, amount, deadline: 11 }.into());
+        assert_eq!(crate::LoanDeadlines::<Test>::get(11).into_inner(), vec![kitty_id]);
+        assert_eq!(KittiesModule::loan_request(kitty_id), None);
+        assert_eq!(Balances::free_balance(borrower), ACCOUNT_BALANCE - KittyPrice::get() + amount);
+        assert_noop!(
+            KittiesModule::sale(RuntimeOrigin::signed(borrower), kitty_id, None, None, None),
+            Error::<Test>::KittyLocked
+        );
+
+        // 只有借款人能还款，还款包含10%利息
+        assert_noop!(
+            KittiesModule::repay_loan(RuntimeOrigin::signed(lender), kitty_id),
+            Error::<Test>::NotBorrower
+        );
+        assert_ok!(KittiesModule::repay_loan(RuntimeOrigin::signed(borrower), kitty_id));
+        System::assert_last_event(Event::LoanRepaid { who: borrower, kitty_id, repayment: 2200 }.into());
+        assert_eq!(Balances::free_balance(lender), ACCOUNT_BALANCE + 200);
+        // 还款后释放到期队列
+        assert!(crate::LoanDeadlines::<Test>::get(11).is_empty());
+
+        // 到期后不再处理已还款的借款
+        run_to_block(11);
+        assert_eq!(KittiesModule::kitty_owner(kitty_id), Some(borrower));
+        assert_ok!(KittiesModule::transfer(RuntimeOrigin::signed(borrower), lender, kitty_id));
+    });
+}
+
+/// Kitty抵押借款到期未还
+#[test]
+fn kitty_loan_defaulted() {
+    build_and_execute(|| {
+        let kitty_id = 0;
+        let borrower = 1;
+        let lender = 2;
+        // 预设余额
+        assert_ok!(Balances::set_balance(RuntimeOrigin::root(), borrower, ACCOUNT_BALANCE, 0));
+        assert_ok!(Balances::set_balance(RuntimeOrigin::root(), lender, ACCOUNT_BALANCE, 0));
+        assert_ok!(KittiesModule::create_kitty(RuntimeOrigin::signed(borrower), KITTY_NAME));
+
+        // 撤销请求
+        assert_ok!(KittiesModule::request_loan(RuntimeOrigin::signed(borrower), kitty_id, 1000, 5));
+        assert_ok!(KittiesModule::cancel_loan_request(RuntimeOrigin::signed(borrower), kitty_id));
+        System::assert_last_event(Event::LoanRequestCancelled { who: borrower, kitty_id }.into());
+        assert_noop!(
+            KittiesModule::fund_loan(RuntimeOrigin::signed(lender), kitty_id),
+            Error::<Test>::NoLoanRequest
+        );
+
+        assert_ok!(KittiesModule::request_loan(RuntimeOrigin::signed(borrower), kitty_id, 1000, 5));
+        assert_ok!(KittiesModule::fund_loan(RuntimeOrigin::signed(lender), kitty_id));
+
+        // 到期未还，kitty归出借人
+        run_to_block(5);
+        assert_eq!(KittiesModule::kitty_owner(kitty_id), Some(borrower));
+        run_to_block(6);
+        System::assert_last_event(Event::LoanDefaulted { lender, borrower, kitty_id }.into());
+        assert_eq!(KittiesModule::kitty_owner(kitty_id), Some(lender));
+        assert_eq!(KittiesModule::loan(kitty_id), None);
+        assert_noop!(
+            KittiesModule::repay_loan(RuntimeOrigin::signed(borrower), kitty_id),
+            Error::<Test>::NoLoan
+        );
+        assert_ok!(KittiesModule::transfer(RuntimeOrigin::signed(lender), borrower, kitty_id));
+    });
+}
+
+/// 创世预置kitty
+#[test]
+fn genesis_kitties() {
+    let mut storage = frame_system::GenesisConfig::default().build_storage::<Test>().unwrap();
+    let config = crate::GenesisConfig::<Test> {
+        kitties: vec![
+            (1, [1u8; 16], *b"genesis0", None),
+            (2, [2u8; 16], *b"genesis1", None),
+            (1, [3u8; 16], *b"genesis2", Some((0, 1))),
+        ],
+        next_kitty_id: 5,
+        phantom: Default::default(),
+    };
+    GenesisBuild::<Test>::assimilate_storage(&config, &mut storage).unwrap();
+    let mut ext: sp_io::TestExternalities = storage.into();
+
+    ext.execute_with(|| {
+        assert_eq!(KittiesModule::next_kitty_id(), 5);
+        assert_eq!(KittiesModule::kitty_owner(0), Some(1));
+        assert_eq!(KittiesModule::kitty_owner(1), Some(2));
+        assert_eq!(KittiesModule::kitty_owner(2), Some(1));
+        assert_eq!(KittiesModule::kitty_parents(2), Some((0, 1)));
+        let kitty = KittiesModule::kitties(2).expect("Genesis Kitty");
+        assert_eq!((kitty.dna, kitty.name, kitty.soulbound), ([3u8; 16], *b"genesis2", false));
+        // 默认实例之外的合集不受影响
+        assert_eq!(KittiesCollection2::next_kitty_id(), 0);
+        assert_ok!(KittiesModule::do_try_state());
+    });
+}
+
+/// 创世预置kitty - 失败：子代在父代之前
+#[test]
+#[should_panic(expected = "must come after its parents")]
+fn genesis_kitties_parents_first() {
+    let mut storage = frame_system::GenesisConfig::default().build_storage::<Test>().unwrap();
+    let config = crate::GenesisConfig::<Test> {
+        kitties: vec![(1, [1u8; 16], *b"genesis0", Some((0, 1))), (2, [2u8; 16], *b"genesis1", None)],
+        next_kitty_id: 2,
+        phantom: Default::default(),
+    };
+    GenesisBuild::<Test>::assimilate_storage(&config, &mut storage).unwrap();
+}
+
+/// 与pallet_balances保持存活的转账检查顺序一致：余额不足，或转账后低于最低余额
+fn keep_alive_transfer(balance: u128, amount: u128) -> Result<(), DispatchError> {
+    if balance < amount {
+        Err(pallet_balances::Error::<Test>::InsufficientBalance.into())
+    } else if balance - amount < EXISTENTIAL_DEPOSIT {
+        Err(pallet_balances::Error::<Test>::KeepAlive.into())
+    } else {
+        Ok(())
+    }
+}
+
+/// 随机操作序列与参考模型对比：每步的错误、所有权、在售、质押状态和余额
+#[test]
+fn random_operations_match_model() {
+    const ACCOUNTS: u64 = 4;
+    const STEPS: u64 = 60;
+
+    for seed in 0..20u64 {
+        build_and_execute(|| {
+            let mut rng = Rng::new(seed);
+            let price = KittyPrice::get();
+            // 参考模型
+            let mut owners: Vec<u64> = Vec::new();
+            let mut listed: Vec<bool> = Vec::new();
+            let mut staked: Vec<Option<u64>> = Vec::new();
+            let mut balances = std::collections::BTreeMap::new();
+            let mut pallet_balance = 0u128;
+            for account_id in 1..=ACCOUNTS {
+                let balance = [3_000u128, 8_000, 20_000][rng.below(3) as usize];
+                assert_ok!(Balances::set_balance(RuntimeOrigin::root(), account_id, balance, 0));
+                balances.insert(account_id, balance);
+            }
+
+            for step in 0..STEPS {
+                // 每个操作一个区块，避免触发创建数量限制
+                run_to_block(step + 2);
+                let who = rng.below(ACCOUNTS) + 1;
+                // 偶尔选择不存在的kitty
+                let kitty_id = rng.below(owners.len() as u64 + 1) as u32;
+                let index = kitty_id as usize;
+                let exists = index < owners.len();
+
+                match rng.below(7) {
+                    0 => {
+                        let expected = keep_alive_transfer(balances[&who], price);
+                        if check(expected, || KittiesModule::create_kitty(RuntimeOrigin::signed(who), KITTY_NAME)) {
+                            owners.push(who);
+                            listed.push(false);
+                            staked.push(None);
+                            *balances.get_mut(&who).unwrap() -= price;
+                            pallet_balance += price;
+                        }
+                    }
+                    1 => {
+                        let recipient = rng.below(ACCOUNTS) + 1;
+                        let expected: Result<(), DispatchError> = if !exists {
+                            Err(Error::<Test>::InvalidKittyId.into())
+                        } else if owners[index] != who {
+                            Err(Error::<Test>::NotOwner.into())
+                        } else if recipient == who {
+                            Err(Error::<Test>::CanNotTransferToSelf.into())
+                        } else if staked[index].is_some() {
+                            Err(Error::<Test>::KittyLocked.into())
+                        } else {
+                            Ok(())
+                        };
+                        if check(expected, || KittiesModule::transfer(RuntimeOrigin::signed(who), recipient, kitty_id)) {
+                            // 转移不会下架
+                            owners[index] = recipient;
+                        }
+                    }
+                    2 => {
+                        let expected: Result<(), DispatchError> = if !exists {
+                            Err(Error::<Test>::InvalidKittyId.into())
+                        } else if owners[index] != who {
+                            Err(Error::<Test>::NotOwner.into())
+                        } else if listed[index] {
+                            Err(Error::<Test>::AlreadyOnSale.into())
+                        } else if staked[index].is_some() {
+                            Err(Error::<Test>::KittyLocked.into())
+                        } else {
+                            Ok(())
+                        };
+                        if check(expected, || KittiesModule::sale(RuntimeOrigin::signed(who), kitty_id, None, None, None)) {
+                            listed[index] = true;
+                        }
+                    }
+                    3 => {
+                        let expected: Result<(), DispatchError> = if !exists {
+                            Err(Error::<Test>::InvalidKittyId.into())
+                        } else if owners[index] == who {
+                            Err(Error::<Test>::AlreadyOwned.into())
+                        } else if !listed[index] {
+                            Err(Error::<Test>::NotOnSale.into())
+                        } else if staked[index].is_some() {
+                            Err(Error::<Test>::KittyLocked.into())
+                        } else {
+                            keep_alive_transfer(balances[&who], price)
+                        };
+                        if check(expected, || KittiesModule::buy(RuntimeOrigin::signed(who), kitty_id)) {
+                            let seller = owners[index];
+                            *balances.get_mut(&who).unwrap() -= price;
+                            *balances.get_mut(&seller).unwrap() += price;
+                            owners[index] = who;
+                            listed[index] = false;
+                        }
+                    }
+                    4 => {
+                        let kitty_id_2 = rng.below(owners.len() as u64 + 1) as u32;
+                        let index_2 = kitty_id_2 as usize;
+                        let expected: Result<(), DispatchError> = if kitty_id == kitty_id_2 {
+                            Err(Error::<Test>::SameKittyId.into())
+                        } else if !exists || index_2 >= owners.len() {
+                            Err(Error::<Test>::InvalidKittyId.into())
+                        } else if owners[index] != who || owners[index_2] != who {
+                            Err(Error::<Test>::NotOwner.into())
+                        } else {
+                            keep_alive_transfer(balances[&who], price)
+                        };
+                        if check(expected, || KittiesModule::breed(RuntimeOrigin::signed(who), kitty_id, kitty_id_2, KITTY_NAME)) {
+                            owners.push(who);
+                            listed.push(false);
+                            staked.push(None);
+                            *balances.get_mut(&who).unwrap() -= price;
+                            pallet_balance += price;
+                        }
+                    }
+                    5 => {
+                        let expected: Result<(), DispatchError> = if !exists {
+                            Err(Error::<Test>::InvalidKittyId.into())
+                        } else if owners[index] != who {
+                            Err(Error::<Test>::NotOwner.into())
+                        } else if listed[index] {
+                            Err(Error::<Test>::AlreadyOnSale.into())
+                        } else if staked[index].is_some() {
+                            Err(Error::<Test>::KittyLocked.into())
+                        } else {
+                            Ok(())
+                        };
+                        if check(expected, || KittiesModule::stake(RuntimeOrigin::signed(who), kitty_id)) {
+                            staked[index] = Some(who);
+                        }
+                    }
+                    _ => {
+                        let expected: Result<(), DispatchError> = match staked.get(index).copied().flatten() {
+                            None => Err(Error::<Test>::NotStaked.into()),
+                            Some(staker) if staker != who => Err(Error::<Test>::NotOwner.into()),
+                            Some(_) => Ok(()),
+                        };
+                        // 奖励按稀有度计算，pallet账户余额不足时不结算
+                        let reward = KittiesModule::pending_reward(kitty_id);
+                        if check(expected, || KittiesModule::unstake(RuntimeOrigin::signed(who), kitty_id)) {
+                            staked[index] = None;
+                            if keep_alive_transfer(pallet_balance, reward).is_ok() {
+                                pallet_balance -= reward;
+                                *balances.get_mut(&who).unwrap() += reward;
+                            }
+                        }
+                    }
+                }
+
+                // 链上状态与模型一致
+                assert_eq!(KittiesModule::next_kitty_id() as usize, owners.len(), "seed {} step {}", seed, step);
+                for (kitty_id, owner) in owners.iter().enumerate() {
+                    let kitty_id = kitty_id as u32;
+                    assert_eq!(KittiesModule::kitty_owner(kitty_id), Some(*owner), "seed {} step {}", seed, step);
+                    assert_eq!(KittiesModule::kitty_on_sale(kitty_id).is_some(), listed[kitty_id as usize], "seed {} step {}", seed, step);
+                    assert_eq!(
+                        KittiesModule::staked_kitty(kitty_id).map(|(staker, _)| staker),
+                        staked[kitty_id as usize],
+                        "seed {} step {}", seed, step
+                    );
+                }
+                for (account_id, balance) in balances.iter() {
+                    assert_eq!(Balances::free_balance(account_id), *balance, "seed {} step {}", seed, step);
+                }
+                assert_eq!(Balances::free_balance(&get_account_id()), pallet_balance, "seed {} step {}", seed, step);
+            }
+        });
+    }
+}
+
+/// 以资产支付购买kitty
+#[test]
+fn buy_kitty_with_asset() {
+    build_and_execute(|| {
+        let kitty_id = 0;
+        let seller = 1;
+        let buyer = 2;
+        let asset_admin = 10;
+        let asset_id = 1u32;
+        // 预设余额
+        assert_ok!(Balances::set_balance(RuntimeOrigin::root(), seller, ACCOUNT_BALANCE, 0));
+        assert_ok!(Balances::set_balance(RuntimeOrigin::root(), buyer, ACCOUNT_BALANCE, 0));
+        assert_ok!(Assets::force_create(RuntimeOrigin::root(), codec::Compact(asset_id), asset_admin, true, 1));
+        assert_ok!(Assets::mint(RuntimeOrigin::signed(asset_admin), codec::Compact(asset_id), buyer, 1000));
+        assert_ok!(KittiesModule::create_kitty(RuntimeOrigin::signed(seller), KITTY_NAME));
+
+        // 以资产定价上架
+        assert_ok!(KittiesModule::sale(RuntimeOrigin::signed(seller), kitty_id, None, None, Some((asset_id, 600))));
+        System::assert_last_event(Event::KittyOnSale {
+            who: seller,
+            kitty_id,
+            buyer: None,
+            asset_price: Some((asset_id, 600)),
+        }.into());
+
+        assert_ok!(KittiesModule::buy(RuntimeOrigin::signed(buyer), kitty_id));
+        assert_eq!(KittiesModule::kitty_owner(kitty_id), Some(buyer));
+        // 以资产结算，原生币余额不变
+        assert_eq!(Assets::balance(asset_id, buyer), 400);
+        assert_eq!(Assets::balance(asset_id, seller), 600);
+        assert_eq!(Balances::free_balance(buyer), ACCOUNT_BALANCE);
+        assert_eq!(Balances::free_balance(seller), ACCOUNT_BALANCE - KittyPrice::get());
+
+        // 资产余额不足不能购买
+        assert_ok!(KittiesModule::sale(RuntimeOrigin::signed(buyer), kitty_id, None, None, Some((asset_id, 601))));
+        assert_noop!(
+            KittiesModule::buy(RuntimeOrigin::signed(seller), kitty_id),
+            pallet_assets::Error::<Test>::BalanceLow
+        );
+    });
+}
+
+/// 持有kitty的账户按kitty数量投票
+#[test]
+fn kitty_poll() {
+    build_and_execute(|| {
+        let alice = 1;
+        let bob = 2;
+        let options = vec![*b"option_a", *b"option_b", *b"option_c"];
+        // 预设余额
+        assert_ok!(Balances::set_balance(RuntimeOrigin::root(), alice, ACCOUNT_BALANCE, 0));
+        assert_ok!(Balances::set_balance(RuntimeOrigin::root(), bob, ACCOUNT_BALANCE, 0));
+        assert_ok!(KittiesModule::batch_create(RuntimeOrigin::signed(alice), vec![KITTY_NAME; 3]));
+        assert_ok!(KittiesModule::create_kitty(RuntimeOrigin::signed(bob), KITTY_NAME));
+        // 快照区块之前取得的kitty才能投票
+        run_to_block(2);
+
+        // 只有root可以创建投票，选项数和结束区块需要有效
+        assert_noop!(
+            KittiesModule::create_poll(RuntimeOrigin::signed(alice), options.clone(), 10),
+            sp_runtime::DispatchError::BadOrigin
+        );
+        assert_noop!(
+            KittiesModule::create_poll(RuntimeOrigin::root(), vec![*b"option_a"], 10),
+            Error::<Test>::InvalidPoll
+        );
+        assert_noop!(
+            KittiesModule::create_poll(RuntimeOrigin::root(), vec![*b"option_a"; 5], 10),
+            Error::<Test>::InvalidPoll
+        );
+        assert_noop!(
+            KittiesModule::create_poll(RuntimeOrigin::root(), options.clone(), System::block_number()),
+            Error::<Test>::InvalidPoll
+        );
+        assert_ok!(KittiesModule::create_poll(RuntimeOrigin::root(), options.clone(), 10));
+        System::assert_last_event(
+            Event::PollCreated { poll_id: 0, options: options.clone().try_into().unwrap(), end: 10, snapshot: 2 }.into()
+        );
+        let poll_id = 0;
+        assert_eq!(KittiesModule::next_poll_id(), 1);
+
+        // 快照之后创建的kitty不能投票
+        assert_ok!(KittiesModule::create_kitty(RuntimeOrigin::signed(bob), KITTY_NAME));
+        assert_noop!(
+            KittiesModule::vote(RuntimeOrigin::signed(bob), poll_id, vec![4], 0),
+            Error::<Test>::NotInSnapshot
+        );
+        assert_noop!(
+            KittiesModule::vote(RuntimeOrigin::signed(bob), poll_id, vec![0], 0),
+            Error::<Test>::NotOwner
+        );
+        assert_noop!(
+            KittiesModule::vote(RuntimeOrigin::signed(alice), poll_id, vec![], 0),
+            Error::<Test>::EmptyVote
+        );
+        assert_noop!(
+            KittiesModule::vote(RuntimeOrigin::signed(alice), poll_id, vec![0], 3),
+            Error::<Test>::InvalidOption
+        );
+        assert_noop!(
+            KittiesModule::vote(RuntimeOrigin::signed(alice), poll_id, vec![0, 0], 1),
+            Error::<Test>::AlreadyVoted
+        );
+
+        assert_ok!(KittiesModule::vote(RuntimeOrigin::signed(alice), poll_id, vec![0, 1], 1));
+        System::assert_last_event(Event::Voted { who: alice, poll_id, option: 1, votes: 2 }.into());
+        assert_eq!(KittiesModule::poll_vote(poll_id, 1), Some(1));
+
+        // 快照之后转移的kitty，新owner不能投票，原owner也不再持有
+        assert_ok!(KittiesModule::transfer(RuntimeOrigin::signed(alice), bob, 2));
+        assert_noop!(
+            KittiesModule::vote(RuntimeOrigin::signed(bob), poll_id, vec![2], 2),
+            Error::<Test>::NotInSnapshot
+        );
+        assert_noop!(
+            KittiesModule::vote(RuntimeOrigin::signed(alice), poll_id, vec![2], 1),
+            Error::<Test>::NotOwner
+        );
+        // 已投票的kitty转移后也不能再次投票
+        assert_ok!(KittiesModule::transfer(RuntimeOrigin::signed(alice), bob, 0));
+        assert_noop!(
+            KittiesModule::vote(RuntimeOrigin::signed(bob), poll_id, vec![0], 2),
+            Error::<Test>::NotInSnapshot
+        );
+        assert_ok!(KittiesModule::vote(RuntimeOrigin::signed(bob), poll_id, vec![3], 2));
+
+        // 结束区块之前不能结束投票
+        assert_noop!(
+            KittiesModule::close_poll(RuntimeOrigin::signed(bob), poll_id),
+            Error::<Test>::PollNotEnded
+        );
+        run_to_block(10);
+        assert_noop!(
+            KittiesModule::vote(RuntimeOrigin::signed(bob), poll_id, vec![3], 0),
+            Error::<Test>::PollEnded
+        );
+        assert_ok!(KittiesModule::close_poll(RuntimeOrigin::signed(bob), poll_id));
+        System::assert_last_event(
+            Event::PollClosed { poll_id, tally: vec![0, 2, 1].try_into().unwrap(), winner: Some(1) }.into()
+        );
+        assert_noop!(
+            KittiesModule::close_poll(RuntimeOrigin::signed(bob), poll_id),
+            Error::<Test>::PollClosed
+        );
+        assert_noop!(
+            KittiesModule::close_poll(RuntimeOrigin::signed(bob), 1),
+            Error::<Test>::NoPoll
+        );
+
+        // 平票或无人投票时没有胜出选项
+        assert_eq!(KittiesModule::poll_winner(&[1, 1, 0]), None);
+        assert_eq!(KittiesModule::poll_winner(&[0, 0]), None);
+        assert_eq!(KittiesModule::poll_winner(&[0, 3, 2]), Some(1));
+    });
+}
+
+/// 盲盒稀有度的抽取由随机数决定，分布与权重一致
+#[test]
+fn mystery_box_distribution() {
+    build_and_execute(|| {
+        let table = [(0, 70), (1, 20), (2, 9), (3, 1)];
+
+        // 随机数的前4字节依次取遍0..总权重，各稀有度的次数恰好等于权重
+        let mut counts = [0u32; 4];
+        for roll in 0u32..100 {
+            let mut seed = [0u8; 16];
+            seed[..4].copy_from_slice(&roll.to_le_bytes());
+            let rarity = KittiesModule::pick_rarity(&table, seed).unwrap();
+            counts[rarity as usize] += 1;
+        }
+        assert_eq!(counts, [70, 20, 9, 1]);
+
+        // 哈希得到的随机数，分布接近权重，生成的dna与稀有度一致
+        let mut counts = [0u32; 4];
+        for i in 0u32..10_000 {
+            let seed = sp_io::hashing::blake2_128(&i.to_le_bytes());
+            assert_eq!(KittiesModule::pick_rarity(&table, seed), KittiesModule::pick_rarity(&table, seed));
+            let rarity = KittiesModule::pick_rarity(&table, seed).unwrap();
+            counts[rarity as usize] += 1;
+
+            let dna = KittiesModule::rarity_dna(rarity, seed).unwrap();
+            let (low, high) = KittiesModule::rarity_range(rarity).unwrap();
+            assert!(dna[0] >= low && dna[0] <= high);
+            assert_eq!(KittiesModule::rarity_weight(&dna), [1, 2, 5, 10][rarity as usize]);
+        }
+        for (count, (_, weight)) in counts.iter().zip(table.iter()) {
+            let expected = weight * 100;
+            assert!(count.abs_diff(expected) <= 200, "count {} expected {}", count, expected);
+        }
+
+        assert_eq!(KittiesModule::pick_rarity(&[], [0; 16]), None);
+        assert_eq!(KittiesModule::rarity_dna(4, [0; 16]), None);
+    });
+}
+
+/// 开启盲盒
+#[test]
+fn open_mystery_box() {
+    build_and_execute(|| {
+        let account_id = 1;
+        let box_id = 0;
+        let price = 500;
+        // 预设余额
+        assert_ok!(Balances::set_balance(RuntimeOrigin::root(), account_id, ACCOUNT_BALANCE, 0));
+
+        assert_noop!(
+            KittiesModule::open_mystery_box(RuntimeOrigin::signed(account_id), box_id, KITTY_NAME),
+            Error::<Test>::NoMysteryBox
+        );
+        // 只有root可以设置，权重表需要有效
+        assert_noop!(
+            KittiesModule::set_mystery_box(RuntimeOrigin::signed(account_id), box_id, price, vec![(0, 1)]),
+            sp_runtime::DispatchError::BadOrigin
+        );
+        for table in [vec![], vec![(4, 1)], vec![(0, 0)], vec![(0, 1), (0, 1)], vec![(0, u32::MAX), (1, 1)]] {
+            assert_noop!(
+                KittiesModule::set_mystery_box(RuntimeOrigin::root(), box_id, price, table),
+                Error::<Test>::InvalidRarityTable
+            );
+        }
+
+        assert_ok!(KittiesModule::set_mystery_box(RuntimeOrigin::root(), box_id, price, vec![(2, 3), (3, 1)]));
+        assert_eq!(
+            KittiesModule::mystery_box_odds(box_id),
+            vec![(2, sp_runtime::Perbill::from_percent(75)), (3, sp_runtime::Perbill::from_percent(25))]
+        );
+        assert_eq!(KittiesModule::mystery_box_odds(1), vec![]);
+
+        // 购买时支付并占用kittyId，稀有度尚未确定
+        assert_ok!(KittiesModule::open_mystery_box(RuntimeOrigin::signed(account_id), box_id, KITTY_NAME));
+        let kitty_id = 0;
+        System::assert_last_event(Event::MysteryBoxCommitted { who: account_id, box_id, kitty_id, reveal_at: 4 }.into());
+        assert!(KittiesModule::kitties(kitty_id).is_none());
+        assert_eq!(KittiesModule::next_kitty_id(), 1);
+        assert_eq!(Balances::free_balance(account_id), ACCOUNT_BALANCE - price);
+        assert_eq!(Balances::free_balance(&get_account_id()), price);
+        assert_noop!(
+            KittiesModule::reveal_mystery_box(RuntimeOrigin::signed(account_id), kitty_id),
+            Error::<Test>::MysteryBoxNotReady
+        );
+
+        // 移除盲盒不影响已购买的
+        assert_ok!(KittiesModule::remove_mystery_box(RuntimeOrigin::root(), box_id));
+        System::assert_last_event(Event::MysteryBoxRemoved { box_id }.into());
+        assert_noop!(
+            KittiesModule::remove_mystery_box(RuntimeOrigin::root(), box_id),
+            Error::<Test>::NoMysteryBox
+        );
+
+        run_to_block(3);
+        assert_noop!(
+            KittiesModule::reveal_mystery_box(RuntimeOrigin::signed(account_id), kitty_id),
+            Error::<Test>::MysteryBoxNotReady
+        );
+        // 随机数在reveal区块确定，任何人都可以开启
+        run_to_block(4);
+        let seed = KittiesModule::reveal_seed(kitty_id).unwrap();
+        let rarity = KittiesModule::pick_rarity(&[(2, 3), (3, 1)], seed).unwrap();
+        assert_ok!(KittiesModule::reveal_mystery_box(RuntimeOrigin::signed(2), kitty_id));
+        System::assert_last_event(Event::MysteryBoxOpened { who: account_id, box_id, kitty_id, rarity }.into());
+        let kitty = KittiesModule::kitties(kitty_id).unwrap();
+        assert_eq!(Some(kitty.dna), KittiesModule::rarity_dna(rarity, seed));
+        assert!(kitty.dna[0] >= 230);
+        assert_eq!(KittiesModule::kitty_owner(kitty_id), Some(account_id));
+        assert_eq!(KittiesModule::reveal_seed(kitty_id), None);
+        assert_noop!(
+            KittiesModule::reveal_mystery_box(RuntimeOrigin::signed(2), kitty_id),
+            Error::<Test>::NoMysteryBoxCommit
+        );
+    });
+}
@@ -1,6 +1,7 @@
 use super::*;
 use crate::{mock::*, Error};
 use frame_support::{assert_noop, assert_ok, BoundedVec};
+use pallet_test_utils::{check, Rng};
 
 ///  创建存证
 #[test]
@@ -157,4 +158,73 @@ fn transfer_claim_fail_when_to_self() {
             Error::<Test>::CanNotTransferToSelf
         );
     });
-}
\ No newline at end of file
+}
+
+/// 随机操作序列与参考模型对比：存证的所有者和创建区块
+#[test]
+fn random_operations_match_model() {
+    const ACCOUNTS: u64 = 3;
+    const STEPS: u64 = 80;
+
+    for seed in 0..20u64 {
+        new_test_ext().execute_with(|| {
+            let mut rng = Rng::new(seed);
+            // 参考模型：存证 -> (所有者, 创建区块)
+            let mut claims = std::collections::BTreeMap::<Vec<u16>, (u64, u64)>::new();
+            // 少量的存证内容，保证操作之间经常冲突；最后一个超出最大长度
+            let candidates: Vec<Vec<u16>> = vec![vec![0], vec![0, 1], vec![2], vec![3; 128], vec![4; 129]];
+
+            for step in 0..STEPS {
+                System::set_block_number(step + 1);
+                let who = rng.below(ACCOUNTS) + 1;
+                let claim = candidates[rng.below(candidates.len() as u64) as usize].clone();
+                let too_long = claim.len() > 128;
+                let existing = claims.get(&claim).cloned();
+
+                match rng.below(3) {
+                    0 => {
+                        let expected = match (too_long, existing) {
+                            (true, _) => Err(Error::<Test>::FailToCreateClaim),
+                            (false, Some(_)) => Err(Error::<Test>::ClaimAlreadyExisted),
+                            (false, None) => Ok(()),
+                        };
+                        if check(expected, || PoeModule::create_claim(RuntimeOrigin::signed(who), claim.clone())) {
+                            claims.insert(claim, (who, step + 1));
+                        }
+                    }
+                    1 => {
+                        let expected = match (too_long, existing) {
+                            (true, _) => Err(Error::<Test>::FailToCreateClaim),
+                            (false, None) => Err(Error::<Test>::NoSuchClaim),
+                            (false, Some((owner, _))) if owner != who => Err(Error::<Test>::NotClaimOwner),
+                            (false, Some(_)) => Ok(()),
+                        };
+                        if check(expected, || PoeModule::revoke_claim(RuntimeOrigin::signed(who), claim.clone())) {
+                            claims.remove(&claim);
+                        }
+                    }
+                    _ => {
+                        let receiver = rng.below(ACCOUNTS) + 1;
+                        let expected = match (too_long, existing) {
+                            (true, _) => Err(Error::<Test>::FailToCreateClaim),
+                            (false, None) => Err(Error::<Test>::NoSuchClaim),
+                            (false, Some((owner, _))) if owner != who => Err(Error::<Test>::NotClaimOwner),
+                            (false, Some(_)) if receiver == who => Err(Error::<Test>::CanNotTransferToSelf),
+                            (false, Some(_)) => Ok(()),
+                        };
+                        if check(expected, || PoeModule::transfer_claim(RuntimeOrigin::signed(who), claim.clone(), receiver)) {
+                            // 转移保留原创建区块
+                            claims.entry(claim).and_modify(|(owner, _)| *owner = receiver);
+                        }
+                    }
+                }
+
+                // 链上状态与模型一致
+                for candidate in candidates.iter().filter(|c| c.len() <= 128) {
+                    let key = BoundedVec::try_from(candidate.clone()).unwrap();
+                    assert_eq!(Claims::<Test>::get(&key), claims.get(candidate).cloned(), "seed {} step {}", seed, step);
+                }
+            }
+        });
+    }
+}
@@ -0,0 +1,36 @@
+//! 各pallet单元测试共用的工具
+
+use frame_support::{assert_noop, assert_ok, dispatch::DispatchResult, sp_runtime::DispatchError};
+
+/// 不依赖外部库的伪随机数生成器(xorshift64*)，保证测试可复现
+pub struct Rng(u64);
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        Rng(seed.wrapping_mul(0x9E37_79B9_7F4A_7C15) | 1)
+    }
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_F491_4F6C_DD1D)
+    }
+    /// [0, n)之间的随机数
+    pub fn below(&mut self, n: u64) -> u64 {
+        self.next_u64() % n
+    }
+}
+
+/// 按模型预期检查调用结果，失败时存储不能有任何变化；返回调用是否成功
+pub fn check<E: Into<DispatchError>>(expected: Result<(), E>, call: impl Fn() -> DispatchResult) -> bool {
+    match expected {
+        Ok(()) => {
+            assert_ok!(call());
+            true
+        }
+        Err(e) => {
+            assert_noop!(call(), e);
+            false
+        }
+    }
+}
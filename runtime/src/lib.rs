@@ -33,7 +33,8 @@ use codec::Encode;
 pub use frame_support::{
     construct_runtime, parameter_types,
     traits::{
-        ConstU128, ConstU32, ConstU64, ConstU8, KeyOwnerProofSystem, Randomness, StorageInfo,
+        AsEnsureOriginWithArg, ConstU128, ConstU32, ConstU64, ConstU8, KeyOwnerProofSystem, Randomness,
+        StorageInfo,
     },
     weights::{
         constants::{
@@ -111,10 +112,10 @@ pub const VERSION: RuntimeVersion = RuntimeVersion {
     //   `spec_version`, and `authoring_version` are the same between Wasm and native.
     // This value is set to 100 to notify Polkadot-JS App (https://polkadot.js.org/apps) to use
     //   the compatible custom types.
-    spec_version: 102,
+    spec_version: 103,
     impl_version: 1,
     apis: RUNTIME_API_VERSIONS,
-    transaction_version: 1,
+    transaction_version: 2,
     state_version: 1,
 };
 
@@ -337,17 +338,81 @@ impl pallet_poe::Config for Runtime {
 parameter_types! {
 	pub KittyPalletId: PalletId = PalletId(*b"zp/kitty");// 长度为8的u8数组
 	pub KittyPrice: Balance = EXISTENTIAL_DEPOSIT * 10;
+	pub AllowlistPrice: Balance = EXISTENTIAL_DEPOSIT * 5;
+	pub BattleStake: Balance = EXISTENTIAL_DEPOSIT * 2;
+	pub BattleReward: Balance = EXISTENTIAL_DEPOSIT;
+	pub FeedPrice: Balance = EXISTENTIAL_DEPOSIT / 5;
+	pub MutationPrice: Balance = EXISTENTIAL_DEPOSIT / 2;
+	pub LoanToValue: Perbill = Perbill::from_percent(50);
+	pub LoanInterest: Perbill = Perbill::from_percent(5);
 }
 impl pallet_kitties::Config for Runtime {
     type RuntimeEvent = RuntimeEvent;
     type Randomness = RandomnessCollectiveFlip;
     type Currency = Balances;
+    type Assets = Assets;
     type KittyPrice = KittyPrice;
     type PalletId = KittyPalletId;
+    type MaxSupply = ConstU32<1_000_000>;
+    type MaxMintsPerBlock = ConstU32<100>;
+    type MaxMintsPerAccount = ConstU32<10>;
+    type ForceOrigin = frame_system::EnsureRoot<AccountId>;
+    type AllowlistPrice = AllowlistPrice;
+    type MaxBundleSize = ConstU32<10>;
+    type MaxBatchSize = ConstU32<32>;
+    type MaxExpiriesPerBlock = ConstU32<64>;
+    type PendingTransferExpiry = ConstU32<DAYS>;
+    type BreedRevealDelay = ConstU32<{ 2 * MINUTES }>;
+    type SwapExpiry = ConstU32<DAYS>;
+    type OffchainSignature = Signature;
+    type OffchainPublic = <Signature as Verify>::Signer;
+    type BattleCooldown = ConstU32<{ 10 * MINUTES }>;
+    type BattleStake = BattleStake;
+    type BattleReward = BattleReward;
+    type FeedPrice = FeedPrice;
+    type XpPerFeed = ConstU32<10>;
+    type XpPerLevel = ConstU32<100>;
+    type MutationLevel = ConstU8<5>;
+    type MutationPrice = MutationPrice;
+    type StakingRewardPerBlock = ConstU128<1>;
+    type LoanToValue = LoanToValue;
+    type LoanInterest = LoanInterest;
+    type MaxPollOptions = ConstU32<16>;
 }
 
 impl pallet_insecure_randomness_collective_flip::Config for Runtime {}
 
+parameter_types! {
+	pub const AssetDeposit: Balance = 100 * EXISTENTIAL_DEPOSIT;
+	pub const AssetAccountDeposit: Balance = EXISTENTIAL_DEPOSIT;
+	pub const MetadataDepositBase: Balance = 10 * EXISTENTIAL_DEPOSIT;
+	pub const MetadataDepositPerByte: Balance = EXISTENTIAL_DEPOSIT / 10;
+	pub const ApprovalDeposit: Balance = EXISTENTIAL_DEPOSIT;
+}
+
+impl pallet_assets::Config for Runtime {
+    type RuntimeEvent = RuntimeEvent;
+    type Balance = Balance;
+    type AssetId = u32;
+    type AssetIdParameter = codec::Compact<u32>;
+    type Currency = Balances;
+    type CreateOrigin = AsEnsureOriginWithArg<frame_system::EnsureSigned<AccountId>>;
+    type ForceOrigin = frame_system::EnsureRoot<AccountId>;
+    type AssetDeposit = AssetDeposit;
+    type AssetAccountDeposit = AssetAccountDeposit;
+    type MetadataDepositBase = MetadataDepositBase;
+    type MetadataDepositPerByte = MetadataDepositPerByte;
+    type ApprovalDeposit = ApprovalDeposit;
+    type StringLimit = ConstU32<50>;
+    type Freezer = ();
+    type Extra = ();
+    type CallbackHandle = ();
+    type WeightInfo = pallet_assets::weights::SubstrateWeight<Runtime>;
+    type RemoveItemsLimit = ConstU32<1000>;
+    #[cfg(feature = "runtime-benchmarks")]
+    type BenchmarkHelper = ();
+}
+
 impl pallet_ocw::Config for Runtime {
     type AuthorityId = pallet_ocw::crypto::TestAuthId;
     type RuntimeEvent = RuntimeEvent;
@@ -375,6 +440,8 @@ construct_runtime!(
 		PoeModule: pallet_poe,
 		KittiesModule: pallet_kitties,
 		OcwModule: pallet_ocw,
+		// 追加在末尾，保持已有模块的索引不变
+		Assets: pallet_assets,
 	}
 );
 
@@ -541,6 +608,28 @@ impl_runtime_apis! {
 		}
 	}
 
+	impl pallet_kitties_runtime_api::KittiesApi<
+		Block,
+		pallet_kitties::KittyId,
+		pallet_kitties::MysteryBoxId,
+		pallet_kitties::ListingOf<Runtime>,
+	> for Runtime {
+		fn kitty_by_name(name: [u8; 8]) -> Option<pallet_kitties::KittyId> {
+			KittiesModule::name_to_kitty(name)
+		}
+
+		fn public_listings(
+			start: Option<pallet_kitties::KittyId>,
+			limit: u32,
+		) -> Vec<(pallet_kitties::KittyId, pallet_kitties::ListingOf<Runtime>)> {
+			KittiesModule::public_listings(start, limit)
+		}
+
+		fn mystery_box_odds(box_id: pallet_kitties::MysteryBoxId) -> Vec<(u8, Perbill)> {
+			KittiesModule::mystery_box_odds(box_id)
+		}
+	}
+
 	impl pallet_transaction_payment_rpc_runtime_api::TransactionPaymentApi<Block, Balance> for Runtime {
 		fn query_info(
 			uxt: <Block as BlockT>::Extrinsic,